@@ -25,13 +25,13 @@ impl State for Counter {
     fn apply(&mut self, action: &CounterAction) -> Result<(), CounterError> {
         match action {
             CounterAction::Increment => {
-                if self.0 == <u32>::max_value() {
+                if self.0 == u32::MAX {
                     return Err(CounterError::MaxValueReached);
                 }
                 self.0 += 1;
             }
             CounterAction::Decrement => {
-                if self.0 == <u32>::min_value() {
+                if self.0 == u32::MIN {
                     return Err(CounterError::MinValueReached);
                 }
                 self.0 -= 1;
@@ -66,7 +66,7 @@ impl Merge for CounterAction {
                 _ => MergeResult::Unmergable,
             },
 
-            CounterAction::SetValue(_) => MergeResult::Overwrites,
+            CounterAction::SetValue(_) => MergeResult::OverwritesState,
         }
     }
 }
@@ -78,13 +78,13 @@ impl State for Counter {
     fn apply(&mut self, action: &CounterAction) -> Result<(), CounterError> {
         match action {
             CounterAction::Increment => {
-                if self.value == <i32>::max_value() {
+                if self.value == i32::MAX {
                     return Err(CounterError::MaxValueReached);
                 }
                 self.value += 1;
             }
             CounterAction::Decrement => {
-                if self.value == <i32>::min_value() {
+                if self.value == i32::MIN {
                     return Err(CounterError::MinValueReached);
                 }
                 self.value -= 1;
@@ -125,12 +125,19 @@ fn merge_actions() -> Result<(), CounterError> {
         chain.len()
     );
 
-    chain.compress();
-    chain.compress();
+    // Keeps compressing until nothing can be merged anymore.
+    let stats = chain.compress_fully();
 
     println!("Length of chain after compressing: {}", chain.len());
+    println!("Compression statistics: {:#?}", stats);
     println!("Chain: {:#?}", chain);
 
+    let mut counter = Counter::default();
+    for action in chain.actions() {
+        counter.apply(action)?;
+    }
+    println!("Value after applying the chain: {}", counter.value);
+
     Ok(())
 }
 
@@ -0,0 +1,154 @@
+use crate::state::State;
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// An action that had another result when it was applied to an equal state
+/// a second time.
+///
+/// `State::apply` should only depend on the state and the action. If it
+/// depends on hidden inputs such as the time, randomness or global state,
+/// undoing and redoing can not restore the state.
+pub struct Nondeterminism<S: State> {
+    /// The state the action was applied to.
+    pub state: S,
+    /// The action.
+    pub action: S::Action,
+    /// The result of applying the action the first time.
+    pub first: Result<S, S::Error>,
+    /// The result of applying the action the second time.
+    pub second: Result<S, S::Error>,
+}
+
+impl<S> fmt::Debug for Nondeterminism<S>
+where
+    S: State + fmt::Debug,
+    S::Action: fmt::Debug,
+    S::Error: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Nondeterminism")
+            .field("state", &self.state)
+            .field("action", &self.action)
+            .field("first", &self.first)
+            .field("second", &self.second)
+            .finish()
+    }
+}
+
+impl<S> fmt::Display for Nondeterminism<S>
+where
+    S: State + fmt::Debug,
+    S::Action: fmt::Debug,
+    S::Error: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "applying {:?} to {:?} twice had different results:",
+            self.action, self.state
+        )?;
+        writeln!(f, "  first:  {:?}", self.first)?;
+        write!(f, "  second: {:?}", self.second)
+    }
+}
+
+pub(crate) fn equal<S: PartialEq>(left: &S, right: &S) -> bool {
+    left == right
+}
+
+pub(crate) fn equal_hashes<S: Hash>(left: &S, right: &S) -> bool {
+    let hash = |state: &S| {
+        let mut hasher = DefaultHasher::new();
+        state.hash(&mut hasher);
+        hasher.finish()
+    };
+    hash(left) == hash(right)
+}
+
+/// Return `true` if both results are equal according to `same`, or if both
+/// are errors.
+pub(crate) fn same_result<S>(
+    left: Result<&S, ()>,
+    right: Result<&S, ()>,
+    same: fn(&S, &S) -> bool,
+) -> bool {
+    match (left, right) {
+        (Ok(left), Ok(right)) => same(left, right),
+        // Why the action was refused does not matter, it was refused both times.
+        (Err(_), Err(_)) => true,
+        _ => false,
+    }
+}
+
+fn audit<S, F>(
+    state: &S,
+    action: &S::Action,
+    between: F,
+    same: fn(&S, &S) -> bool,
+) -> Result<(), Nondeterminism<S>>
+where
+    S: State + Clone,
+    S::Action: Clone,
+    F: FnOnce(),
+{
+    let apply = || {
+        let mut copy = state.clone();
+        copy.apply(action).map(|()| copy)
+    };
+
+    let first = apply();
+    between();
+    let second = apply();
+
+    let refused = |_| ();
+    if same_result(
+        first.as_ref().map_err(refused),
+        second.as_ref().map_err(refused),
+        same,
+    ) {
+        return Ok(());
+    }
+
+    Err(Nondeterminism {
+        state: state.clone(),
+        action: action.clone(),
+        first,
+        second,
+    })
+}
+
+/// Check that applying `action` to `state` is deterministic.
+///
+/// The action is applied to two copies of `state`, and `between` is called
+/// in between, for example to let time pass or to change global state. The
+/// results are the same if both states are equal, or if both applies fail.
+///
+/// # Return
+/// `Nondeterminism` with both results if they are not the same.
+pub fn audit_apply<S, F>(state: &S, action: &S::Action, between: F) -> Result<(), Nondeterminism<S>>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone,
+    F: FnOnce(),
+{
+    audit(state, action, between, equal)
+}
+
+/// Check that applying `action` to `state` is deterministic, like
+/// `audit_apply`, but compare the hashes of the resulting states.
+///
+/// Useful for states that implement `Hash` but not `PartialEq`.
+pub fn audit_apply_hashed<S, F>(
+    state: &S,
+    action: &S::Action,
+    between: F,
+) -> Result<(), Nondeterminism<S>>
+where
+    S: State + Clone + Hash,
+    S::Action: Clone,
+    F: FnOnce(),
+{
+    audit(state, action, between, equal_hashes)
+}
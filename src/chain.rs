@@ -1,6 +1,175 @@
-use crate::merge::{Merge, MergeResult};
+use crate::merge::{Commute, Merge, MergeResult};
+use crate::state::{InverseResult, State};
 
-#[derive(Clone, Debug)]
+use std::collections::VecDeque;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{Index, IndexMut, RangeBounds};
+use std::slice::{self, SliceIndex};
+use std::vec;
+
+/// What `Chain::apply_to` does when applying an action fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Stop at the first failing action.
+    /// The actions before it stay applied.
+    StopOnError,
+    /// Skip failing actions and continue with the next one.
+    SkipFailing,
+    /// Stop at the first failing action and undo the actions before it.
+    ///
+    /// `Chain::apply_to_with_rollback` always restores the state.
+    /// `Chain::apply_to` can only undo actions that have an inverse action.
+    RollbackOnError,
+}
+
+/// The outcome of applying a chain with `Chain::apply_to`.
+#[derive(Debug)]
+pub struct ApplyReport<E> {
+    /// The indices of the actions that were applied successfully.
+    pub succeeded: Vec<usize>,
+    /// The indices of the actions that failed, with the reason.
+    pub failed: Vec<(usize, E)>,
+    /// `true` if the state was restored by `ErrorPolicy::RollbackOnError`.
+    /// The succeeded actions no longer have any effect in that case.
+    pub rolled_back: bool,
+}
+
+impl<E> ApplyReport<E> {
+    /// Return `true` if every action was applied.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// A function that returns the action that restores the state it is given,
+/// see `FullCopyPolicy::Restore`.
+pub type RestoreFn<S> = Box<dyn Fn(&S) -> <S as State>::Action>;
+
+/// What `Chain::inverse` does with actions for which the state returns
+/// `InverseResult::FullCopyRequired`.
+pub enum FullCopyPolicy<S: State> {
+    /// Fail with `InverseError::FullCopyRequired`.
+    Fail,
+    /// Call the function with the state from before the action, and use the
+    /// action it returns to restore that state.
+    Restore(RestoreFn<S>),
+}
+
+/// The error that is returned by `Chain::inverse`.
+#[derive(Debug)]
+pub enum InverseError<E> {
+    /// Applying the action at `index` to the scratch copy of the state failed.
+    Apply {
+        /// The index of the action in the chain.
+        index: usize,
+        /// The error returned by the state.
+        error: E,
+    },
+    /// The action at `index` has no inverse action and
+    /// `FullCopyPolicy::Fail` was used.
+    FullCopyRequired {
+        /// The index of the action in the chain.
+        index: usize,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for InverseError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InverseError::Apply { index, error } => {
+                write!(f, "Applying action {} failed: {}", index, error)
+            }
+            InverseError::FullCopyRequired { index } => {
+                write!(f, "Action {} can only be undone with a full copy.", index)
+            }
+        }
+    }
+}
+
+/// Statistics about a call to `Chain::compress_fully`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompressStats {
+    /// The number of passes over the chain, including the last pass
+    /// that did not change anything.
+    pub passes: usize,
+    /// The number of times two actions were merged into one.
+    pub merges: usize,
+    /// The number of times two actions cancelled each other out.
+    pub cancellations: usize,
+    /// The number of times an action overwrote the previous action
+    /// or the whole state.
+    pub overwrites: usize,
+    /// The number of times two actions were replaced by other actions.
+    pub replacements: usize,
+    /// The length of the chain before compressing.
+    pub len_before: usize,
+    /// The length of the chain after compressing.
+    pub len_after: usize,
+}
+
+impl CompressStats {
+    /// Return the number of times two actions were merged, cancelled out,
+    /// overwritten or replaced.
+    fn changes(&self) -> usize {
+        self.merges + self.cancellations + self.overwrites + self.replacements
+    }
+}
+
+/// How many times compression follows `MergeResult::Replace` for every
+/// action of the input before it treats the actions as unmergable.
+pub(crate) const MAX_REPLACEMENTS_PER_ACTION: usize = 8;
+
+/// Merge `action` into the end of the already compressed actions in `result`.
+///
+/// This is a single step of a compression pass. Actions from
+/// `MergeResult::Replace` are pushed onto `pending` in reverse order, and
+/// should be passed to this function before the next action of the input.
+pub(crate) fn compress_step<A: Merge>(
+    result: &mut VecDeque<A>,
+    action: A,
+    pending: &mut Vec<A>,
+    replacements_left: &mut usize,
+    stats: &mut CompressStats,
+) {
+    let merged = match result.back() {
+        None => MergeResult::Unmergable,
+        Some(previous) => action.merge(previous),
+    };
+
+    match merged {
+        MergeResult::Unmergable => result.push_back(action),
+        MergeResult::CancelsOut => {
+            result.pop_back();
+            stats.cancellations += 1;
+        }
+        MergeResult::OverwritesPrevious => {
+            *result.back_mut().unwrap() = action;
+            stats.overwrites += 1;
+        }
+        MergeResult::OverwritesState => {
+            result.clear();
+            result.push_back(action);
+            stats.overwrites += 1;
+        }
+        MergeResult::Merged(merged) => {
+            *result.back_mut().unwrap() = merged;
+            stats.merges += 1;
+        }
+        MergeResult::Replace(actions) => {
+            if *replacements_left == 0 {
+                result.push_back(action);
+            } else {
+                *replacements_left -= 1;
+                result.pop_back();
+                pending.extend(actions.into_iter().rev());
+                stats.replacements += 1;
+            }
+        }
+    };
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 /// A 'chain' of actions.
 ///
 /// Represents a number of consecutive actions.
@@ -21,51 +190,277 @@ impl<A: Sized + Clone> Chain<A> {
     ///
     /// Executing the compressed chain should result in the exact
     /// same mutation of the data as the original chain.
+    ///
+    /// Every action is merged with the action before it in a single pass.
+    /// A merged action is not merged again with the action before it, use
+    /// `compress_fully` for that.
     pub fn compress(&mut self)
     where
         A: Merge,
     {
-        let length_old = self.chain.len();
+        // A single pass ends, so only the budget per action is needed.
+        let mut replacements_left = usize::MAX;
+        self.compress_pass(&mut replacements_left, &mut CompressStats::default());
+    }
 
-        if length_old <= 1 {
-            return;
+    /// Compress the chain until it can not be compressed any further.
+    ///
+    /// Repeats the pass of `compress` until nothing changes.
+    ///
+    /// # Return
+    /// Statistics about the compression.
+    pub fn compress_fully(&mut self) -> CompressStats
+    where
+        A: Merge,
+    {
+        let mut stats = CompressStats {
+            len_before: self.chain.len(),
+            ..CompressStats::default()
+        };
+
+        // The budget is shared by every pass, so that actions that keep
+        // replacing each other can not make the passes go on forever.
+        let mut replacements_left = self.replacement_budget();
+        while self.compress_pass(&mut replacements_left, &mut stats) {}
+
+        stats.len_after = self.chain.len();
+        stats
+    }
+
+    /// Return how many times `MergeResult::Replace` is followed in total when
+    /// the chain is compressed with more than a single pass.
+    fn replacement_budget(&self) -> usize {
+        self.chain.len().saturating_mul(MAX_REPLACEMENTS_PER_ACTION)
+    }
+
+    /// Run a single pass of compression, which follows `MergeResult::Replace`
+    /// at most `replacements_left` times in total.
+    ///
+    /// # Return
+    /// `true` if the pass changed the chain.
+    fn compress_pass(&mut self, replacements_left: &mut usize, stats: &mut CompressStats) -> bool
+    where
+        A: Merge,
+    {
+        stats.passes += 1;
+        let changes_old = stats.changes();
+
+        // Actions from `MergeResult::Replace` are compressed again before the
+        // rest of the chain.
+        let mut pending: Vec<A> = Vec::new();
+        let input = std::mem::take(&mut self.chain);
+        let mut result: VecDeque<A> = VecDeque::with_capacity(input.len());
+
+        for action in input {
+            // Like a push to a `Compressor`, every action of the input may
+            // follow `Replace` a bounded number of times.
+            let budget = MAX_REPLACEMENTS_PER_ACTION.min(*replacements_left);
+            let mut left = budget;
+            pending.push(action);
+            while let Some(action) = pending.pop() {
+                compress_step(&mut result, action, &mut pending, &mut left, stats);
+            }
+            *replacements_left -= budget - left;
+        }
+
+        self.chain = result.into();
+        stats.changes() > changes_old
+    }
+
+    /// Apply every action in the chain to `state`, without keeping any history.
+    ///
+    /// `policy` decides what happens when an action fails.
+    /// With `ErrorPolicy::RollbackOnError` the applied actions are undone
+    /// with their inverse actions. If one of them needs a full copy of the
+    /// state, or an inverse action fails, the state is left as it is and
+    /// `rolled_back` is `false`. Use `apply_to_with_rollback` if the state
+    /// can be cloned.
+    ///
+    /// # Return
+    /// A report of which actions succeeded and which failed, and why.
+    pub fn apply_to<S>(&self, state: &mut S, policy: ErrorPolicy) -> ApplyReport<S::Error>
+    where
+        S: State<Action = A>,
+    {
+        let mut report = ApplyReport {
+            succeeded: Vec::with_capacity(self.chain.len()),
+            failed: Vec::new(),
+            rolled_back: false,
+        };
+        // The inverses of the applied actions, as long as all of them have one.
+        let mut undo = match policy {
+            ErrorPolicy::RollbackOnError => Some(Vec::new()),
+            _ => None,
+        };
+
+        for (index, action) in self.chain.iter().enumerate() {
+            let inverse = undo.as_ref().map(|_| state.inverse(action));
+            match state.apply(action) {
+                Ok(()) => {
+                    report.succeeded.push(index);
+                    undo = match (undo, inverse) {
+                        (Some(mut undo), Some(InverseResult::Action(inverse))) => {
+                            undo.push(inverse);
+                            Some(undo)
+                        }
+                        _ => None,
+                    };
+                }
+                Err(e) => {
+                    report.failed.push((index, e));
+
+                    match policy {
+                        ErrorPolicy::SkipFailing => continue,
+                        ErrorPolicy::StopOnError => break,
+                        ErrorPolicy::RollbackOnError => {
+                            if let Some(undo) = undo {
+                                report.rolled_back =
+                                    undo.iter().rev().all(|action| state.apply(action).is_ok());
+                            }
+                            break;
+                        }
+                    }
+                }
+            }
         }
 
-        let mut result: Vec<Option<A>> = vec![None; length_old];
-        let mut i_write = 0;
-        let mut i_read = 0;
+        report
+    }
+
+    /// Apply every action in the chain to `state`, like `apply_to`.
+    ///
+    /// With `ErrorPolicy::RollbackOnError` a copy of the state is kept while
+    /// the chain is applied, and the state is always restored if an action
+    /// fails. The other policies behave as in `apply_to`.
+    pub fn apply_to_with_rollback<S>(
+        &self,
+        state: &mut S,
+        policy: ErrorPolicy,
+    ) -> ApplyReport<S::Error>
+    where
+        S: State<Action = A> + Clone,
+    {
+        if policy != ErrorPolicy::RollbackOnError {
+            return self.apply_to(state, policy);
+        }
 
-        while i_read < length_old {
-            let current_action = &self.chain[i_read];
+        let backup = state.clone();
+        let mut report = self.apply_to(state, ErrorPolicy::StopOnError);
+        if !report.is_success() {
+            *state = backup;
+            report.rolled_back = true;
+        }
+        report
+    }
 
-            if i_write == 0 {
-                result[i_write] = Some(self.chain[i_read].clone());
-                i_write += 1;
-            } else {
-                match (*current_action).merge(&result[i_write - 1].clone().unwrap()) {
+    /// Return the chain that undoes this chain when it is applied to the
+    /// result of applying this chain to `state`.
+    ///
+    /// The chain is applied to a scratch copy of `state` to find the inverse
+    /// of every action. `policy` decides what happens with actions that can
+    /// only be undone with a full copy of the state.
+    pub fn inverse<S>(
+        &self,
+        state: &S,
+        policy: FullCopyPolicy<S>,
+    ) -> Result<Chain<A>, InverseError<S::Error>>
+    where
+        S: State<Action = A> + Clone,
+    {
+        let mut scratch = state.clone();
+        let mut inverse = Vec::with_capacity(self.chain.len());
+
+        for (index, action) in self.chain.iter().enumerate() {
+            let undo = match (scratch.inverse(action), &policy) {
+                (InverseResult::Action(undo), _) => undo,
+                (InverseResult::FullCopyRequired, FullCopyPolicy::Restore(restore)) => {
+                    restore(&scratch)
+                }
+                (InverseResult::FullCopyRequired, FullCopyPolicy::Fail) => {
+                    return Err(InverseError::FullCopyRequired { index });
+                }
+            };
+
+            scratch
+                .apply(action)
+                .map_err(|error| InverseError::Apply { index, error })?;
+            inverse.push(undo);
+        }
+
+        inverse.reverse();
+        Ok(Self { chain: inverse })
+    }
+
+    /// Compress the chain, also merging actions that are not adjacent.
+    ///
+    /// Like `compress`, but an action that can not be merged with the action
+    /// before it is moved further back past every action it commutes with,
+    /// until it can be merged or meets an action it does not commute with.
+    pub fn compress_commuting(&mut self)
+    where
+        A: Merge + Commute,
+    {
+        let mut replacements_left = self.replacement_budget();
+        let mut pending: Vec<A> = Vec::new();
+        let mut input = std::mem::take(&mut self.chain).into_iter();
+        let mut result: Vec<A> = Vec::with_capacity(input.len());
+
+        while let Some(action) = pending.pop().or_else(|| input.next()) {
+            // `action` can be moved to directly after `result[i - 1]`.
+            let mut i = result.len();
+
+            loop {
+                if i == 0 {
+                    result.push(action);
+                    break;
+                }
+
+                let merged = match action.merge(&result[i - 1]) {
+                    MergeResult::Replace(_) if replacements_left == 0 => MergeResult::Unmergable,
+                    merged => merged,
+                };
+                match merged {
                     MergeResult::Unmergable => {
-                        result[i_write] = Some(current_action.clone());
-                        i_write += 1;
+                        if action.commutes_with(&result[i - 1]) {
+                            i -= 1;
+                        } else {
+                            result.push(action);
+                            break;
+                        }
                     }
                     MergeResult::CancelsOut => {
-                        i_write -= 1;
+                        result.remove(i - 1);
+                        break;
                     }
-                    MergeResult::Overwrites => {
-                        result[0] = Some(current_action.clone());
-                        i_write = 1;
+                    MergeResult::OverwritesPrevious => {
+                        result[i - 1] = action;
+                        break;
                     }
-                    MergeResult::Merged(action) => {
-                        result[i_write - 1] = Some(action);
+                    MergeResult::OverwritesState => {
+                        result.clear();
+                        result.push(action);
+                        break;
                     }
-                };
+                    MergeResult::Merged(merged) => {
+                        result[i - 1] = merged;
+                        break;
+                    }
+                    MergeResult::Replace(actions) => {
+                        // The new actions take the place of `result[i - 1]`.
+                        // They and the actions `action` was moved past are
+                        // compressed again, in order.
+                        replacements_left -= 1;
+                        let moved_past = result.split_off(i);
+                        result.pop();
+                        pending.extend(moved_past.into_iter().rev());
+                        pending.extend(actions.into_iter().rev());
+                        break;
+                    }
+                }
             }
-
-            i_read += 1;
         }
 
-        result.truncate(i_write);
-
-        self.chain = result.into_iter().map(|x| x.unwrap()).collect();
+        self.chain = result;
     }
 
     /// Return a new empty chain of actions.
@@ -92,6 +487,78 @@ impl<A: Sized + Clone> Chain<A> {
         self.chain.len()
     }
 
+    /// Return `true` if the chain contains no actions.
+    pub fn is_empty(&self) -> bool {
+        self.chain.is_empty()
+    }
+
+    /// Return an iterator over the actions, from first to last.
+    pub fn iter(&self) -> slice::Iter<'_, A> {
+        self.chain.iter()
+    }
+
+    /// Return an iterator that allows modifying each action.
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, A> {
+        self.chain.iter_mut()
+    }
+
+    /// Insert an action at position `index`, shifting all actions after it.
+    ///
+    /// # Panics
+    /// If `index` is larger than the length of the chain.
+    pub fn insert(&mut self, index: usize, action: A) {
+        self.chain.insert(index, action);
+    }
+
+    /// Remove and return the action at position `index`, shifting all
+    /// actions after it.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> A {
+        self.chain.remove(index)
+    }
+
+    /// Replace the actions in `range` by the actions of `replace_with`.
+    ///
+    /// Works like `Vec::splice`: the removed actions are returned as an
+    /// iterator, and the replacement happens when the iterator is dropped.
+    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> vec::Splice<'_, I::IntoIter>
+    where
+        R: RangeBounds<usize>,
+        I: IntoIterator<Item = A>,
+    {
+        self.chain.splice(range, replace_with)
+    }
+
+    /// Split the chain in two at `at`.
+    ///
+    /// `self` keeps the actions `[0, at)`, the returned chain contains the
+    /// actions `[at, len)`.
+    ///
+    /// # Panics
+    /// If `at` is larger than the length of the chain.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        Self {
+            chain: self.chain.split_off(at),
+        }
+    }
+
+    /// Move all actions of `other` to the back of this chain, leaving `other` empty.
+    pub fn append(&mut self, other: &mut Self) {
+        self.chain.append(&mut other.chain);
+    }
+
+    /// Keep only the actions for which `keep` returns `true`.
+    ///
+    /// The order of the remaining actions is preserved.
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&A) -> bool,
+    {
+        self.chain.retain(keep);
+    }
+
     /// Clear the chain.
     ///
     /// This will remove any action currenty in the chain.
@@ -101,9 +568,15 @@ impl<A: Sized + Clone> Chain<A> {
     }
 }
 
-impl<A: Sized + Clone> Into<Vec<A>> for Chain<A> {
-    fn into(self) -> Vec<A> {
-        self.chain
+impl<A: Sized + Clone> Default for Chain<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Sized + Clone> From<Chain<A>> for Vec<A> {
+    fn from(chain: Chain<A>) -> Self {
+        chain.chain
     }
 }
 
@@ -112,3 +585,64 @@ impl<A: Sized + Clone> From<Vec<A>> for Chain<A> {
         Self { chain: vec }
     }
 }
+
+impl<A: Sized + Clone> FromIterator<A> for Chain<A> {
+    fn from_iter<I: IntoIterator<Item = A>>(iter: I) -> Self {
+        Self {
+            chain: Vec::from_iter(iter),
+        }
+    }
+}
+
+impl<A: Sized + Clone> Extend<A> for Chain<A> {
+    fn extend<I: IntoIterator<Item = A>>(&mut self, iter: I) {
+        self.chain.extend(iter);
+    }
+}
+
+impl<'a, A: Sized + Clone> Extend<&'a A> for Chain<A> {
+    fn extend<I: IntoIterator<Item = &'a A>>(&mut self, iter: I) {
+        self.chain.extend(iter.into_iter().cloned());
+    }
+}
+
+impl<A: Sized + Clone> IntoIterator for Chain<A> {
+    type Item = A;
+    type IntoIter = vec::IntoIter<A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chain.into_iter()
+    }
+}
+
+impl<'a, A: Sized + Clone> IntoIterator for &'a Chain<A> {
+    type Item = &'a A;
+    type IntoIter = slice::Iter<'a, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chain.iter()
+    }
+}
+
+impl<'a, A: Sized + Clone> IntoIterator for &'a mut Chain<A> {
+    type Item = &'a mut A;
+    type IntoIter = slice::IterMut<'a, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chain.iter_mut()
+    }
+}
+
+impl<A: Sized + Clone, I: SliceIndex<[A]>> Index<I> for Chain<A> {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        &self.chain[index]
+    }
+}
+
+impl<A: Sized + Clone, I: SliceIndex<[A]>> IndexMut<I> for Chain<A> {
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        &mut self.chain[index]
+    }
+}
@@ -0,0 +1,116 @@
+use crate::chain::Chain;
+use crate::merge::Merge;
+
+use std::fmt;
+
+/// Trait that describes actions to a user.
+pub trait Describe {
+    /// The kind of the action, used to group entries in a `Changelog`.
+    /// For example "Moved objects".
+    fn kind(&self) -> String;
+
+    /// A sentence that describes the action.
+    /// For example "You moved the player 5 steps to the left."
+    fn describe(&self) -> String;
+}
+
+/// All entries of a `Changelog` that are of the same kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeGroup {
+    /// The kind of the actions, as returned by `Describe::kind`.
+    pub kind: String,
+    /// The descriptions of the actions, in the order they were applied.
+    pub entries: Vec<String>,
+}
+
+/// A list of changes that can be shown to a user.
+///
+/// The actions are compressed first, so that only the minimal actions
+/// required to get to the new state remain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Changelog {
+    groups: Vec<ChangeGroup>,
+}
+
+impl Changelog {
+    /// Create a changelog of the actions in `chain`.
+    ///
+    /// Groups are ordered by the first action of their kind.
+    pub fn from_chain<A>(chain: &Chain<A>) -> Self
+    where
+        A: Clone + Merge + Describe,
+    {
+        let mut compressed = chain.clone();
+        compressed.compress();
+
+        let mut groups: Vec<ChangeGroup> = Vec::new();
+        for action in compressed.actions() {
+            let kind = action.kind();
+            let entry = action.describe();
+
+            match groups.iter_mut().find(|group| group.kind == kind) {
+                Some(group) => group.entries.push(entry),
+                None => groups.push(ChangeGroup {
+                    kind,
+                    entries: vec![entry],
+                }),
+            }
+        }
+
+        Self { groups }
+    }
+
+    /// Return the groups of entries.
+    pub fn groups(&self) -> &[ChangeGroup] {
+        &self.groups
+    }
+
+    /// Return the total number of entries.
+    pub fn len(&self) -> usize {
+        self.groups.iter().map(|group| group.entries.len()).sum()
+    }
+
+    /// Return `true` if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Render the changelog as plain text.
+    ///
+    /// This is the same as formatting it with `Display`.
+    pub fn to_text(&self) -> String {
+        self.to_string()
+    }
+
+    /// Render the changelog as Markdown, with a heading for every kind.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+
+        for (i, group) in self.groups.iter().enumerate() {
+            if i > 0 {
+                markdown.push('\n');
+            }
+            markdown.push_str(&format!("## {}\n\n", group.kind));
+            for entry in &group.entries {
+                markdown.push_str(&format!("- {}\n", entry));
+            }
+        }
+
+        markdown
+    }
+}
+
+impl fmt::Display for Changelog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, group) in self.groups.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{} ({}):", group.kind, group.entries.len())?;
+            for entry in &group.entries {
+                writeln!(f, "  - {}", entry)?;
+            }
+        }
+        Ok(())
+    }
+}
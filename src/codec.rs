@@ -0,0 +1,168 @@
+use std::io::{self, Read, Write};
+
+/// Trait that converts a value to and from bytes.
+///
+/// It is used wherever states or actions are written to a drive, for example
+/// by the file-backed history stores of a `Timeline`.
+/// Implementations for the primitive types, `String`, `Vec`, `Option` and
+/// `Box` are provided. Integers are stored little-endian with a fixed width.
+pub trait Codec
+where
+    Self: Sized,
+{
+    /// Write the value to `writer`.
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    /// Read a value that was written by `encode` from `reader`.
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+/// Write `value` as a variable-length integer (LEB128).
+///
+/// Small values take a single byte, the largest values take 10 bytes.
+pub fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// Read a variable-length integer that was written by `write_varint`.
+pub fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = u8::decode(reader)?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            if shift == 63 && byte > 1 {
+                break;
+            }
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("varint is too long"))
+}
+
+/// Return the error used when decoding meets bytes that can not be valid.
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+macro_rules! codec_int {
+    ($($int:ty),*) => {
+        $(
+            impl Codec for $int {
+                fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+
+                fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+                    let mut bytes = [0; std::mem::size_of::<$int>()];
+                    reader.read_exact(&mut bytes)?;
+                    Ok(<$int>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+codec_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Codec for usize {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u64).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let value = u64::decode(reader)?;
+        if value > usize::MAX as u64 {
+            return Err(invalid_data("usize out of range for this platform"));
+        }
+        Ok(value as usize)
+    }
+}
+
+impl Codec for bool {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (*self as u8).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::decode(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("invalid bool")),
+        }
+    }
+}
+
+impl Codec for String {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.len().encode(writer)?;
+        writer.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let length = usize::decode(reader)?;
+        let mut bytes = Vec::new();
+        reader.take(length as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(|_| invalid_data("invalid UTF-8 in string"))
+    }
+}
+
+impl<T: Codec> Codec for Vec<T> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.len().encode(writer)?;
+        for item in self {
+            item.encode(writer)?;
+        }
+        Ok(())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let length = usize::decode(reader)?;
+        // Do not trust the length for the allocation, the data may be damaged.
+        let mut items = Vec::with_capacity(length.min(1024));
+        for _ in 0..length {
+            items.push(T::decode(reader)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<T: Codec> Codec for Option<T> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            None => 0u8.encode(writer),
+            Some(value) => {
+                1u8.encode(writer)?;
+                value.encode(writer)
+            }
+        }
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::decode(reader)? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(reader)?)),
+            _ => Err(invalid_data("invalid option tag")),
+        }
+    }
+}
+
+impl<T: Codec> Codec for Box<T> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        (**self).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(Box::new(T::decode(reader)?))
+    }
+}
@@ -0,0 +1,191 @@
+use crate::chain::{compress_step, CompressStats, MAX_REPLACEMENTS_PER_ACTION};
+use crate::merge::Merge;
+
+use std::collections::VecDeque;
+
+/// Compresses actions one at a time, as they arrive.
+///
+/// The actions are compressed with the same single pass as `Chain::compress`,
+/// so a `Compressor` created with `new` returns exactly what
+/// `Chain::compress` returns for the same actions, once it is finished.
+/// Every compressed action is kept until then, because a later action can
+/// still cancel it out or overwrite it.
+///
+/// A `Compressor` created with `with_window` keeps at most `window`
+/// compressed actions instead, so that its memory stays bounded. Older
+/// actions leave the window and are returned by `push`; they will not
+/// change anymore. This is windowed compression: the output has the same
+/// effect as the input, but later actions can not cancel out or overwrite
+/// the actions that left the window, so it can be longer than the output of
+/// `Chain::compress`.
+pub struct Compressor<A: Merge> {
+    window: Option<usize>,
+    tail: VecDeque<A>,
+    pending: Vec<A>,
+    emitted: usize,
+    stats: CompressStats,
+}
+
+impl<A: Merge> Default for Compressor<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Merge> Compressor<A> {
+    /// Create a new `Compressor` that keeps every compressed action until it
+    /// is finished, and returns exactly what `Chain::compress` returns.
+    pub fn new() -> Self {
+        Self {
+            window: None,
+            tail: VecDeque::new(),
+            pending: Vec::new(),
+            emitted: 0,
+            stats: CompressStats {
+                passes: 1,
+                ..CompressStats::default()
+            },
+        }
+    }
+
+    /// Create a new `Compressor` that keeps at most `window` actions.
+    ///
+    /// A window of 0 is treated as a window of 1.
+    pub fn with_window(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            window: Some(window),
+            tail: VecDeque::with_capacity(window + 1),
+            ..Self::new()
+        }
+    }
+
+    /// Add an action after the actions pushed so far.
+    ///
+    /// # Return
+    /// The compressed actions that left the window, in order.
+    pub fn push(&mut self, action: A) -> Vec<A> {
+        self.stats.len_before += 1;
+        let mut replacements_left = MAX_REPLACEMENTS_PER_ACTION;
+
+        let mut done = Vec::new();
+        self.pending.push(action);
+        while let Some(action) = self.pending.pop() {
+            compress_step(
+                &mut self.tail,
+                action,
+                &mut self.pending,
+                &mut replacements_left,
+                &mut self.stats,
+            );
+
+            if let Some(window) = self.window {
+                while self.tail.len() > window {
+                    done.extend(self.tail.pop_front());
+                }
+            }
+        }
+
+        self.emitted += done.len();
+        done
+    }
+
+    /// Return every action in the window.
+    ///
+    /// Actions pushed afterwards are not merged with these actions.
+    pub fn flush(&mut self) -> Vec<A> {
+        self.emitted += self.tail.len();
+        self.tail.drain(..).collect()
+    }
+
+    /// Return every action in the window and stop compressing.
+    pub fn finish(mut self) -> Vec<A> {
+        self.flush()
+    }
+
+    /// Return the compressed actions that are still in the window.
+    pub fn tail(&self) -> impl Iterator<Item = &A> {
+        self.tail.iter()
+    }
+
+    /// Return the maximum number of actions kept in the window, or `None` if
+    /// every compressed action is kept.
+    pub fn window(&self) -> Option<usize> {
+        self.window
+    }
+
+    /// Return statistics about the actions pushed so far.
+    ///
+    /// `len_after` counts both the actions that were returned and the
+    /// actions that are still in the window.
+    pub fn stats(&self) -> CompressStats {
+        CompressStats {
+            len_after: self.emitted + self.tail.len(),
+            ..self.stats
+        }
+    }
+}
+
+/// An iterator that compresses the actions of another iterator.
+///
+/// Created by `CompressActions::compress_actions`.
+pub struct CompressedActions<I: Iterator>
+where
+    I::Item: Merge,
+{
+    input: I,
+    compressor: Compressor<I::Item>,
+    output: VecDeque<I::Item>,
+    finished: bool,
+}
+
+impl<I: Iterator> Iterator for CompressedActions<I>
+where
+    I::Item: Merge,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.output.is_empty() && !self.finished {
+            match self.input.next() {
+                Some(action) => self.output.extend(self.compressor.push(action)),
+                None => {
+                    self.output.extend(self.compressor.flush());
+                    self.finished = true;
+                }
+            }
+        }
+        self.output.pop_front()
+    }
+}
+
+/// Extension trait that compresses iterators of actions.
+pub trait CompressActions: Iterator + Sized
+where
+    Self::Item: Merge,
+{
+    /// Compress the actions with `Compressor::new`, like `Chain::compress`.
+    ///
+    /// The first action is only returned once the input is exhausted.
+    fn compress_actions(self) -> CompressedActions<Self> {
+        CompressedActions {
+            input: self,
+            compressor: Compressor::new(),
+            output: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Compress the actions with `Compressor::with_window`, which keeps at
+    /// most `window` actions.
+    fn compress_actions_within(self, window: usize) -> CompressedActions<Self> {
+        CompressedActions {
+            input: self,
+            compressor: Compressor::with_window(window),
+            output: VecDeque::new(),
+            finished: false,
+        }
+    }
+}
+
+impl<I: Iterator> CompressActions for I where I::Item: Merge {}
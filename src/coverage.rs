@@ -0,0 +1,116 @@
+use crate::state::State;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::mem::size_of_val;
+
+/// How often the actions of one kind had an inverse when they were applied
+/// to a `Timeline`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InverseCounts {
+    /// The number of actions that had an inverse action.
+    pub inverses: usize,
+    /// The number of actions that required a full copy of the state.
+    pub full_copies: usize,
+    /// The number of bytes of the full copies.
+    pub bytes_copied: usize,
+}
+
+/// How often the actions applied to a `Timeline` had an inverse, per kind
+/// of action. See `Timeline::track_inverses`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InverseReport {
+    /// The counts per kind of action.
+    pub kinds: BTreeMap<String, InverseCounts>,
+}
+
+impl InverseReport {
+    /// Return the counts of all kinds of actions together.
+    pub fn total(&self) -> InverseCounts {
+        let mut total = InverseCounts::default();
+        for counts in self.kinds.values() {
+            total.inverses += counts.inverses;
+            total.full_copies += counts.full_copies;
+            total.bytes_copied += counts.bytes_copied;
+        }
+        total
+    }
+}
+
+impl fmt::Display for InverseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (kind, counts) in &self.kinds {
+            writeln!(
+                f,
+                "{}: {} inverses, {} full copies ({} bytes)",
+                kind, counts.inverses, counts.full_copies, counts.bytes_copied
+            )?;
+        }
+        let total = self.total();
+        write!(
+            f,
+            "total: {} inverses, {} full copies ({} bytes)",
+            total.inverses, total.full_copies, total.bytes_copied
+        )
+    }
+}
+
+/// A function that returns the kind of an action, see
+/// `Timeline::track_inverses_with`.
+pub type KindFn<A> = Box<dyn Fn(&A) -> String>;
+
+/// A function that returns the bytes of a full copy of a state, see
+/// `Timeline::track_inverses_with`.
+pub type SizeFn<S> = Box<dyn Fn(&S) -> usize>;
+
+/// Return the name of the variant of `action`, from its `Debug` output.
+///
+/// Actions that are not enums, such as numbers, booleans or strings, have no
+/// variant name in their `Debug` output, so the name of their type is
+/// returned. Variant names are expected to start with an uppercase letter.
+pub(crate) fn variant_name<A: fmt::Debug>(action: &A) -> String {
+    let name: String = format!("{:?}", action)
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    match name.chars().next() {
+        Some(first) if first.is_uppercase() => name,
+        _ => std::any::type_name::<A>().to_string(),
+    }
+}
+
+/// Return the number of bytes that cloning `state` copies inline.
+pub(crate) fn inline_size<S>(state: &S) -> usize {
+    size_of_val(state)
+}
+
+/// Counts the inverses of the actions applied to a `Timeline`.
+pub(crate) struct InverseTracker<S: State> {
+    pub(crate) kind: KindFn<S::Action>,
+    pub(crate) size: SizeFn<S>,
+    pub(crate) report: InverseReport,
+}
+
+impl<S: State> InverseTracker<S> {
+    /// Return the kind of `action`, and the bytes of the full copy of the
+    /// state it required if it had no inverse.
+    pub(crate) fn measure(
+        &self,
+        action: &S::Action,
+        full_copy: Option<&S>,
+    ) -> (String, Option<usize>) {
+        ((self.kind)(action), full_copy.map(&self.size))
+    }
+
+    /// Count an applied action of `kind`, measured by `measure`.
+    pub(crate) fn record(&mut self, (kind, bytes_copied): (String, Option<usize>)) {
+        let counts = self.report.kinds.entry(kind).or_default();
+        match bytes_copied {
+            Some(bytes) => {
+                counts.full_copies += 1;
+                counts.bytes_copied += bytes;
+            }
+            None => counts.inverses += 1,
+        }
+    }
+}
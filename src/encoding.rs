@@ -0,0 +1,309 @@
+use crate::chain::Chain;
+use crate::codec::{invalid_data, read_varint, write_varint, Codec};
+use crate::error::LoadError;
+use crate::format::FormatHeader;
+use crate::integrity::{Corruption, IntegrityError, Linking, RecordReader, RecordWriter};
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+/// Actions are collected in blocks of about this many bytes.
+/// Every block is written as one checksummed record.
+const BLOCK_SIZE: usize = 4096;
+
+const BLOCK_ACTIONS: u8 = 0;
+const BLOCK_TRAILER: u8 = 1;
+
+const FLAG_DICTIONARY: u8 = 1;
+
+/// Writes a `Chain` of actions in a compact binary format, one action at a time.
+///
+/// The data starts with a `FormatHeader`, followed by hash-chained records:
+/// one record with the options, blocks of actions, and a trailer holding the
+/// number of actions so that missing blocks are noticed.
+/// Every action is stored as a varint length followed by its `Codec` encoding.
+///
+/// With a dictionary, an action that was written before is stored as a varint
+/// reference to its first occurrence instead.
+pub struct ChainEncoder<W: Write, A: Codec> {
+    records: RecordWriter<W>,
+    block: Vec<u8>,
+    scratch: Vec<u8>,
+    dictionary: Option<HashMap<Vec<u8>, u64>>,
+    dictionary_limit: u64,
+    count: u64,
+    action: PhantomData<A>,
+}
+
+impl<W: Write, A: Codec> ChainEncoder<W, A> {
+    /// Create a new `ChainEncoder` that writes to `writer`.
+    pub fn new(writer: W) -> io::Result<Self> {
+        Self::create(writer, None)
+    }
+
+    /// Create a new `ChainEncoder` that writes to `writer` and replaces
+    /// repeated actions by references.
+    ///
+    /// At most `limit` distinct actions are remembered, which bounds the
+    /// memory used by both the encoder and the decoder.
+    pub fn with_dictionary(writer: W, limit: usize) -> io::Result<Self> {
+        Self::create(writer, Some(limit as u64))
+    }
+
+    fn create(mut writer: W, dictionary_limit: Option<u64>) -> io::Result<Self> {
+        let header = FormatHeader {
+            magic: FormatHeader::CHAIN_MAGIC,
+            version: FormatHeader::CHAIN_VERSION,
+        };
+        header.write(&mut writer)?;
+
+        let mut records = RecordWriter::new(writer, Linking::Chained);
+        let mut options = Vec::new();
+        match dictionary_limit {
+            Some(limit) => {
+                options.push(FLAG_DICTIONARY);
+                write_varint(&mut options, limit)?;
+            }
+            None => options.push(0),
+        }
+        records.write_record(&options)?;
+
+        Ok(Self {
+            records,
+            block: vec![BLOCK_ACTIONS],
+            scratch: Vec::new(),
+            dictionary: dictionary_limit.map(|_| HashMap::new()),
+            dictionary_limit: dictionary_limit.unwrap_or(0),
+            count: 0,
+            action: PhantomData,
+        })
+    }
+
+    /// Add an action after the actions written so far.
+    pub fn push(&mut self, action: &A) -> io::Result<()> {
+        self.scratch.clear();
+        action.encode(&mut self.scratch)?;
+
+        match self.dictionary {
+            None => self.write_literal()?,
+            Some(ref mut dictionary) => match dictionary.get(&self.scratch) {
+                // 0 marks a literal, references are shifted by one.
+                Some(index) => write_varint(&mut self.block, index + 1)?,
+                None => {
+                    if (dictionary.len() as u64) < self.dictionary_limit {
+                        let index = dictionary.len() as u64;
+                        dictionary.insert(self.scratch.clone(), index);
+                    }
+                    write_varint(&mut self.block, 0)?;
+                    self.write_literal()?;
+                }
+            },
+        }
+
+        self.count += 1;
+        if self.block.len() >= BLOCK_SIZE {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    /// Write the remaining actions and the trailer.
+    ///
+    /// # Return
+    /// The underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_block()?;
+
+        let mut trailer = vec![BLOCK_TRAILER];
+        write_varint(&mut trailer, self.count)?;
+        self.records.write_record(&trailer)?;
+        self.records.flush()?;
+
+        Ok(self.records.into_inner())
+    }
+
+    fn write_literal(&mut self) -> io::Result<()> {
+        write_varint(&mut self.block, self.scratch.len() as u64)?;
+        self.block.extend_from_slice(&self.scratch);
+        Ok(())
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        if self.block.len() > 1 {
+            self.records.write_record(&self.block)?;
+            self.block.truncate(1);
+        }
+        Ok(())
+    }
+}
+
+/// Reads a `Chain` that was written by a `ChainEncoder`, one action at a time.
+///
+/// Only one block of encoded actions is kept in memory.
+/// Every action is returned as a `Result`; after the first error no more
+/// actions are returned.
+pub struct ChainDecoder<R: Read, A: Codec + Clone> {
+    records: RecordReader<R>,
+    block: Vec<u8>,
+    position: usize,
+    dictionary: Option<Vec<A>>,
+    dictionary_limit: u64,
+    count: u64,
+    done: bool,
+}
+
+impl<R: Read, A: Codec + Clone> ChainDecoder<R, A> {
+    /// Create a new `ChainDecoder` that reads from `reader`.
+    pub fn new(mut reader: R) -> Result<Self, LoadError> {
+        // Older versions of the format are migrated here once they exist.
+        match FormatHeader::expect(&mut reader, FormatHeader::CHAIN_MAGIC)? {
+            FormatHeader::CHAIN_VERSION => {}
+            version => return Err(LoadError::UnsupportedVersion(version)),
+        }
+
+        let mut records = RecordReader::new(reader, Linking::Chained);
+        let options = records.read_expected()?;
+        let mut options = &options[..];
+
+        let (dictionary, dictionary_limit) = match u8::decode(&mut options)? {
+            0 => (None, 0),
+            FLAG_DICTIONARY => (Some(Vec::new()), read_varint(&mut options)?),
+            _ => return Err(invalid_data("unknown chain options").into()),
+        };
+
+        Ok(Self {
+            records,
+            block: Vec::new(),
+            position: 0,
+            dictionary,
+            dictionary_limit,
+            count: 0,
+            done: false,
+        })
+    }
+
+    fn next_action(&mut self) -> Result<Option<A>, LoadError> {
+        while self.position >= self.block.len() {
+            self.block = self.records.read_expected()?;
+            self.position = 1;
+
+            match self.block.first() {
+                Some(&BLOCK_ACTIONS) => {}
+                Some(&BLOCK_TRAILER) => {
+                    let count = read_varint(&mut &self.block[1..])?;
+                    if count != self.count {
+                        return Err(self.corrupt(Corruption::Truncated));
+                    }
+                    self.records.expect_end()?;
+                    return Ok(None);
+                }
+                _ => return Err(invalid_data("unknown block kind").into()),
+            }
+        }
+
+        let mut items = &self.block[self.position..];
+        let remaining = items.len();
+        let bad_length = self.corrupt(Corruption::BadLength);
+
+        let action = match self.dictionary {
+            None => read_literal(&mut items, bad_length)?,
+            Some(ref mut dictionary) => match read_varint(&mut items)? {
+                0 => {
+                    let action: A = read_literal(&mut items, bad_length)?;
+                    if (dictionary.len() as u64) < self.dictionary_limit {
+                        dictionary.push(action.clone());
+                    }
+                    action
+                }
+                index => match dictionary.get(index as usize - 1) {
+                    Some(action) => action.clone(),
+                    None => return Err(invalid_data("unknown dictionary reference").into()),
+                },
+            },
+        };
+
+        self.position += remaining - items.len();
+        self.count += 1;
+        Ok(Some(action))
+    }
+
+    fn corrupt(&self, kind: Corruption) -> LoadError {
+        LoadError::Corrupt(IntegrityError {
+            record: self.records.records_read() - 1,
+            kind,
+        })
+    }
+}
+
+impl<R: Read, A: Codec + Clone> Iterator for ChainDecoder<R, A> {
+    type Item = Result<A, LoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.next_action() {
+            Ok(Some(action)) => Some(Ok(action)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Read an action that is stored with its length, or return `bad_length`
+/// if decoding it does not take exactly that many bytes.
+fn read_literal<A: Codec>(items: &mut &[u8], bad_length: LoadError) -> Result<A, LoadError> {
+    let length = read_varint(items)? as usize;
+    if length > items.len() {
+        return Err(invalid_data("action is longer than its block").into());
+    }
+
+    let (mut encoded, rest) = items.split_at(length);
+    *items = rest;
+    let action = A::decode(&mut encoded)?;
+    if !encoded.is_empty() {
+        return Err(bad_length);
+    }
+    Ok(action)
+}
+
+impl<A: Codec + Clone> Chain<A> {
+    /// Write the chain to `writer` in the compact binary format of `ChainEncoder`.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = ChainEncoder::new(writer)?;
+        for action in self.actions() {
+            encoder.push(action)?;
+        }
+        encoder.finish().map(|_| ())
+    }
+
+    /// Write the chain to `writer` like `write_to`, but store repeated
+    /// actions as references. See `ChainEncoder::with_dictionary`.
+    pub fn write_to_with_dictionary<W: Write>(&self, writer: W, limit: usize) -> io::Result<()> {
+        let mut encoder = ChainEncoder::with_dictionary(writer, limit)?;
+        for action in self.actions() {
+            encoder.push(action)?;
+        }
+        encoder.finish().map(|_| ())
+    }
+
+    /// Read a chain that was written by `write_to` or a `ChainEncoder`.
+    ///
+    /// # Return
+    /// The chain, or `LoadError::Corrupt` if the data was damaged or edited.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, LoadError> {
+        let mut chain = Chain::new();
+        for action in ChainDecoder::new(reader)? {
+            chain.push(action?);
+        }
+        Ok(chain)
+    }
+}
@@ -0,0 +1,123 @@
+use crate::chain::{Chain, ErrorPolicy};
+use crate::state::State;
+
+use std::fmt;
+
+/// The result of applying a chain to a state, as compared by `check_equivalence`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome<S: State> {
+    /// Every action was applied, resulting in this state.
+    Applied(S),
+    /// The action at `index` in the chain failed.
+    Failed {
+        /// The index of the action that failed.
+        index: usize,
+        /// The error returned by `State::apply`.
+        error: S::Error,
+    },
+}
+
+impl<S: State + Clone> Outcome<S> {
+    pub(crate) fn of(chain: &Chain<S::Action>, initial: &S) -> Self
+    where
+        S::Action: Clone,
+    {
+        let mut state = initial.clone();
+        let mut report = chain.apply_to(&mut state, ErrorPolicy::StopOnError);
+        match report.failed.pop() {
+            None => Outcome::Applied(state),
+            Some((index, error)) => Outcome::Failed { index, error },
+        }
+    }
+
+    pub(crate) fn matches(&self, other: &Self) -> bool
+    where
+        S: PartialEq,
+    {
+        match (self, other) {
+            (Outcome::Applied(left), Outcome::Applied(right)) => left == right,
+            // Which action failed and why does not matter, both chains
+            // refuse the state.
+            (Outcome::Failed { .. }, Outcome::Failed { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A state for which two chains that should be equivalent are not.
+pub struct Counterexample<S: State> {
+    /// The number of states that were checked before this state.
+    pub checked: usize,
+    /// The state both chains were applied to.
+    pub initial: S,
+    /// The result of applying the first chain.
+    pub left: Outcome<S>,
+    /// The result of applying the second chain.
+    pub right: Outcome<S>,
+}
+
+impl<S> fmt::Debug for Counterexample<S>
+where
+    S: State + fmt::Debug,
+    S::Error: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Counterexample")
+            .field("checked", &self.checked)
+            .field("initial", &self.initial)
+            .field("left", &self.left)
+            .field("right", &self.right)
+            .finish()
+    }
+}
+
+impl<S> fmt::Display for Counterexample<S>
+where
+    S: State + fmt::Debug,
+    S::Error: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "the chains differ when applied to {:?}:", self.initial)?;
+        writeln!(f, "  left:  {:?}", self.left)?;
+        write!(f, "  right: {:?}", self.right)
+    }
+}
+
+/// Check that `left` and `right` have the same effect on every state in `states`.
+///
+/// Both chains are applied to a copy of every state. They are equivalent for
+/// a state if the resulting states are equal, or if both chains fail.
+/// This can be used to test a `Merge` implementation, by comparing a chain
+/// with its compressed copy.
+///
+/// # Return
+/// The number of states that were checked, or the first state for which the
+/// chains are not equivalent.
+pub fn check_equivalence<S, I>(
+    left: &Chain<S::Action>,
+    right: &Chain<S::Action>,
+    states: I,
+) -> Result<usize, Counterexample<S>>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone,
+    I: IntoIterator<Item = S>,
+{
+    let mut checked = 0;
+
+    for initial in states {
+        let left = Outcome::of(left, &initial);
+        let right = Outcome::of(right, &initial);
+        if !left.matches(&right) {
+            return Err(Counterexample {
+                checked,
+                initial,
+                left,
+                right,
+            });
+        }
+        checked += 1;
+    }
+
+    Ok(checked)
+}
@@ -1,7 +1,9 @@
+use crate::integrity::IntegrityError;
 use crate::state::State;
 use crate::timeline::TimelineError;
 use std::error::Error as StdError;
 use std::fmt;
+use std::io;
 
 /// An errortype that is used by any function in the actions-library that can return an error.
 pub enum Error<S: State> {
@@ -50,3 +52,61 @@ where
     S::Error: fmt::Display + fmt::Debug,
 {
 }
+
+/// The error that is returned when loading persisted data fails.
+#[derive(Debug)]
+pub enum LoadError {
+    /// Reading from the underlying reader failed.
+    Io(io::Error),
+    /// The data was damaged or tampered with.
+    Corrupt(IntegrityError),
+    /// The data does not start with the expected format header.
+    BadMagic,
+    /// The data was written in a version of the format this crate can not read.
+    UnsupportedVersion(u16),
+}
+
+impl From<io::Error> for LoadError {
+    fn from(source: io::Error) -> LoadError {
+        LoadError::Io(source)
+    }
+}
+
+impl From<IntegrityError> for LoadError {
+    fn from(source: IntegrityError) -> LoadError {
+        LoadError::Corrupt(source)
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "[Load error]: {}", e),
+            LoadError::Corrupt(e) => write!(f, "[Load error]: {}", e),
+            LoadError::BadMagic => write!(f, "[Load error]: Not a file of the expected kind."),
+            LoadError::UnsupportedVersion(v) => {
+                write!(f, "[Load error]: Unsupported format version {}.", v)
+            }
+        }
+    }
+}
+
+// Lets loading code be used where only an `io::Error` can be returned,
+// such as inside a `HistoryStore`.
+impl From<LoadError> for io::Error {
+    fn from(source: LoadError) -> io::Error {
+        match source {
+            LoadError::Io(e) => e,
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
+impl StdError for LoadError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
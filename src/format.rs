@@ -0,0 +1,72 @@
+use crate::error::LoadError;
+
+use std::io::{self, Read, Write};
+
+/// The header at the start of every file written by this crate.
+///
+/// It tells what kind of data follows and which version of the format it was
+/// written in, so that newer versions of the crate can recognise and migrate
+/// files written by older versions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormatHeader {
+    /// Identifies the kind of data, for example `b"ACTL"` for a `Timeline`.
+    pub magic: [u8; 4],
+    /// The version of the format.
+    pub version: u16,
+}
+
+impl FormatHeader {
+    /// Magic bytes of a saved `Timeline`.
+    pub const TIMELINE_MAGIC: [u8; 4] = *b"ACTL";
+
+    /// The version of the `Timeline` format that is written by this crate.
+    pub const TIMELINE_VERSION: u16 = 1;
+
+    /// Magic bytes of an encoded `Chain`.
+    pub const CHAIN_MAGIC: [u8; 4] = *b"ACHN";
+
+    /// The version of the `Chain` format that is written by this crate.
+    pub const CHAIN_VERSION: u16 = 1;
+
+    /// Magic bytes of a `Recording` of a session.
+    pub const RECORDING_MAGIC: [u8; 4] = *b"ACRS";
+
+    /// The version of the `Recording` format that is written by this crate.
+    pub const RECORDING_VERSION: u16 = 1;
+
+    /// Write the header to `writer`.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.magic)?;
+        writer.write_all(&self.version.to_le_bytes())
+    }
+
+    /// Read a header from `reader`.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, LoadError> {
+        let mut bytes = [0; 6];
+        reader.read_exact(&mut bytes).map_err(|e| {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                LoadError::BadMagic
+            } else {
+                LoadError::Io(e)
+            }
+        })?;
+
+        Ok(Self {
+            magic: [bytes[0], bytes[1], bytes[2], bytes[3]],
+            version: u16::from_le_bytes([bytes[4], bytes[5]]),
+        })
+    }
+
+    /// Read a header from `reader` and check that it starts with `magic`.
+    ///
+    /// # Return
+    /// The version of the format, or `LoadError::BadMagic` if the data is
+    /// of another kind.
+    pub fn expect<R: Read>(reader: &mut R, magic: [u8; 4]) -> Result<u16, LoadError> {
+        let header = Self::read(reader)?;
+        if header.magic != magic {
+            return Err(LoadError::BadMagic);
+        }
+        Ok(header.version)
+    }
+}
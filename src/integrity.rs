@@ -0,0 +1,292 @@
+use crate::error::LoadError;
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// Starting value of the hash chain, the 64 bit FNV offset basis.
+const LINK_SEED: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Whether records are linked to the record before them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Linking {
+    /// Every record only carries its own checksum.
+    Unlinked,
+    /// Every record also carries a hash over its own content and the hash of
+    /// the record before it.
+    ///
+    /// Removing, duplicating or reordering records breaks the chain, even if
+    /// every record on its own is intact.
+    Chained,
+}
+
+/// The kind of damage that was found in a record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corruption {
+    /// The stored checksum does not match the content of the record.
+    ChecksumMismatch,
+    /// The record is intact, but it does not follow the record before it.
+    BrokenLink,
+    /// The data ended in the middle of the record, or records are missing
+    /// at the end.
+    Truncated,
+    /// The record is intact, but it is not expected at this place.
+    Unexpected,
+    /// The record is intact, but an item in it is not as long as the
+    /// length stored for it.
+    BadLength,
+}
+
+/// Describes the first corrupt record that was found in persisted data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntegrityError {
+    /// Index of the corrupt record, counting from 0.
+    pub record: usize,
+    /// What is wrong with the record.
+    pub kind: Corruption,
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            Corruption::ChecksumMismatch => "checksum mismatch",
+            Corruption::BrokenLink => "broken hash chain",
+            Corruption::Truncated => "data ends early",
+            Corruption::Unexpected => "record does not belong here",
+            Corruption::BadLength => "item does not match its length",
+        };
+        write!(f, "Record {} is corrupt: {}.", self.record, kind)
+    }
+}
+
+impl std::error::Error for IntegrityError {}
+
+/// Writes records of bytes, each followed by a checksum and, if chained,
+/// a link to the record before it.
+///
+/// Every record is laid out as
+/// `length (u32 LE) | payload | crc32 (u32 LE) | link (u64 LE, chained only)`.
+/// The checksum covers the length as well as the payload.
+pub struct RecordWriter<W: Write> {
+    inner: W,
+    linking: Linking,
+    link: u64,
+    records: usize,
+}
+
+impl<W: Write> RecordWriter<W> {
+    /// Create a new `RecordWriter` that writes to `inner`.
+    pub fn new(inner: W, linking: Linking) -> Self {
+        Self {
+            inner,
+            linking,
+            link: LINK_SEED,
+            records: 0,
+        }
+    }
+
+    /// Write one record.
+    pub fn write_record(&mut self, payload: &[u8]) -> io::Result<()> {
+        if payload.len() > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "record is larger than 4 GiB",
+            ));
+        }
+        let length = (payload.len() as u32).to_le_bytes();
+
+        self.inner.write_all(&length)?;
+        self.inner.write_all(payload)?;
+        self.inner
+            .write_all(&crc32(&[&length, payload]).to_le_bytes())?;
+
+        if self.linking == Linking::Chained {
+            self.link = next_link(self.link, &length, payload);
+            self.inner.write_all(&self.link.to_le_bytes())?;
+        }
+
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Return the number of records written so far.
+    pub fn records_written(&self) -> usize {
+        self.records
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Return the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads records written by a [`RecordWriter`] and checks their integrity.
+pub struct RecordReader<R: Read> {
+    inner: R,
+    linking: Linking,
+    link: u64,
+    records: usize,
+}
+
+impl<R: Read> RecordReader<R> {
+    /// Create a new `RecordReader` that reads from `inner`.
+    ///
+    /// `linking` has to match the `Linking` the records were written with.
+    pub fn new(inner: R, linking: Linking) -> Self {
+        Self {
+            inner,
+            linking,
+            link: LINK_SEED,
+            records: 0,
+        }
+    }
+
+    /// Read the next record.
+    ///
+    /// # Return
+    /// `Ok(None)` if the data ends cleanly after the previous record,
+    /// `LoadError::Corrupt` if the record does not pass the integrity checks.
+    pub fn read_record(&mut self) -> Result<Option<Vec<u8>>, LoadError> {
+        let mut length = [0; 4];
+        match read_full(&mut self.inner, &mut length)? {
+            0 => return Ok(None),
+            4 => {}
+            _ => return Err(self.corrupt(Corruption::Truncated)),
+        }
+
+        // Read through `take` so that a damaged length can not make us
+        // allocate more than the data actually contains.
+        let expected = u32::from_le_bytes(length) as u64;
+        let mut payload = Vec::new();
+        (&mut self.inner).take(expected).read_to_end(&mut payload)?;
+        if payload.len() as u64 != expected {
+            return Err(self.corrupt(Corruption::Truncated));
+        }
+
+        let mut checksum = [0; 4];
+        if read_full(&mut self.inner, &mut checksum)? != 4 {
+            return Err(self.corrupt(Corruption::Truncated));
+        }
+        if u32::from_le_bytes(checksum) != crc32(&[&length, &payload]) {
+            return Err(self.corrupt(Corruption::ChecksumMismatch));
+        }
+
+        if self.linking == Linking::Chained {
+            let mut link = [0; 8];
+            if read_full(&mut self.inner, &mut link)? != 8 {
+                return Err(self.corrupt(Corruption::Truncated));
+            }
+            let expected_link = next_link(self.link, &length, &payload);
+            if u64::from_le_bytes(link) != expected_link {
+                return Err(self.corrupt(Corruption::BrokenLink));
+            }
+            self.link = expected_link;
+        }
+
+        self.records += 1;
+        Ok(Some(payload))
+    }
+
+    /// Read the next record, which has to exist.
+    ///
+    /// A clean end of the data is reported as `Corruption::Truncated`.
+    pub fn read_expected(&mut self) -> Result<Vec<u8>, LoadError> {
+        match self.read_record()? {
+            Some(payload) => Ok(payload),
+            None => Err(self.corrupt(Corruption::Truncated)),
+        }
+    }
+
+    /// Check that the data ends after the previous record.
+    ///
+    /// Any further record is reported as `Corruption::Unexpected`.
+    pub fn expect_end(&mut self) -> Result<(), LoadError> {
+        match self.read_record()? {
+            None => Ok(()),
+            Some(_) => {
+                self.records -= 1;
+                Err(self.corrupt(Corruption::Unexpected))
+            }
+        }
+    }
+
+    /// Return the number of records read so far.
+    pub fn records_read(&self) -> usize {
+        self.records
+    }
+
+    /// Return the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn corrupt(&self, kind: Corruption) -> LoadError {
+        LoadError::Corrupt(IntegrityError {
+            record: self.records,
+            kind,
+        })
+    }
+}
+
+/// Check every record in `reader` without keeping them.
+///
+/// # Return
+/// The number of intact records, or an error describing the first corrupt record.
+pub fn verify_records<R: Read>(reader: R, linking: Linking) -> Result<usize, LoadError> {
+    let mut reader = RecordReader::new(reader, linking);
+    while reader.read_record()?.is_some() {}
+    Ok(reader.records_read())
+}
+
+/// Fill `buf` as far as possible and return the number of bytes read.
+/// Only returns less than `buf.len()` at the end of the data.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// CRC-32 (IEEE) over the concatenation of `parts`.
+pub(crate) fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for part in parts {
+        for byte in part.iter() {
+            crc ^= u32::from(*byte);
+            for _ in 0..8 {
+                let mask = (crc & 1).wrapping_neg();
+                crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+            }
+        }
+    }
+    !crc
+}
+
+/// FNV-1a over the previous link, the length and the payload.
+///
+/// This detects accidental damage and careless hand edits.
+/// It is not a cryptographic hash and does not protect against forgery.
+fn next_link(previous: u64, length: &[u8], payload: &[u8]) -> u64 {
+    let mut hash = LINK_SEED;
+    for byte in previous
+        .to_le_bytes()
+        .iter()
+        .chain(length.iter())
+        .chain(payload.iter())
+    {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
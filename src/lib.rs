@@ -14,16 +14,59 @@
 //! - Create *macro's* which merge multiple actions into one chain of actions!
 
 mod merge;
-pub use self::merge::{Merge, MergeResult};
+pub use self::merge::{Commute, Merge, MergeResult};
+
+mod audit;
+pub use self::audit::{audit_apply, audit_apply_hashed, Nondeterminism};
 
 mod chain;
-pub use self::chain::Chain;
+pub use self::chain::{
+    ApplyReport, Chain, CompressStats, ErrorPolicy, FullCopyPolicy, InverseError, RestoreFn,
+};
+
+mod changelog;
+pub use self::changelog::{ChangeGroup, Changelog, Describe};
+
+mod codec;
+pub use self::codec::{read_varint, write_varint, Codec};
+
+mod compressor;
+pub use self::compressor::{CompressActions, CompressedActions, Compressor};
+
+mod coverage;
+pub use self::coverage::{InverseCounts, InverseReport, KindFn, SizeFn};
+
+mod encoding;
+pub use self::encoding::{ChainDecoder, ChainEncoder};
+
+mod equivalence;
+pub use self::equivalence::{check_equivalence, Counterexample, Outcome};
 
 mod error;
-pub use self::error::Error;
+pub use self::error::{Error, LoadError};
+
+mod format;
+pub use self::format::FormatHeader;
+
+mod integrity;
+pub use self::integrity::{
+    verify_records, Corruption, IntegrityError, Linking, RecordReader, RecordWriter,
+};
+
+mod optimal;
+pub use self::optimal::{Cost, OptimalStats, SearchBudget};
+
+mod recorder;
+pub use self::recorder::{OpOutcome, RecordedOp, Recording, ReplayMismatch, SessionRecorder};
 
 mod state;
 pub use self::state::{InverseResult, State};
 
+mod store;
+pub use self::store::{Breadcrumb, Entry, FileStore, HistoryStore, MemoryStore, SpillStore};
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
 mod timeline;
-pub use self::timeline::Timeline;
+pub use self::timeline::{Timeline, TimelineError, TimelineOp};
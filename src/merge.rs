@@ -4,12 +4,25 @@ pub enum MergeResult<Action: Sized> {
     CancelsOut,
     /// The actions cannot be merged.
     Unmergable,
+    /// The action fully overwrites the effect of the previous action.
+    /// In this case, the previous action can be dropped,
+    /// but the actions before it still matter.
+    OverwritesPrevious,
     /// The action fully overwrites the whole state.
     /// In this case, it does not matter how the previous
-    /// action influenced the state. It is overwritten anyway.
-    Overwrites,
+    /// actions influenced the state. It is overwritten anyway,
+    /// so every action before it can be dropped.
+    OverwritesState,
     /// The actions are merged into one action.
     Merged(Action),
+    /// The actions are replaced by other actions. Applying these actions in
+    /// order has the same effect as applying the previous action and then
+    /// this action.
+    ///
+    /// Can be used to bring actions into a canonical order, or to turn
+    /// overlapping actions into disjoint ones. The new actions are compressed
+    /// again, so they should not keep replacing each other.
+    Replace(Vec<Action>),
 }
 
 /// Trait that enables actions to merge actions.
@@ -25,3 +38,15 @@ where
     /// previous: The previous action.
     fn merge(&self, previous: &Self) -> MergeResult<Self>;
 }
+
+/// Trait that tells whether two actions can be applied in either order.
+///
+/// Used by `Chain::compress_commuting` to move actions next to actions
+/// they can be merged with.
+pub trait Commute {
+    /// Return `true` if applying `self` and then `other` always has the
+    /// exact same effect as applying `other` and then `self`.
+    ///
+    /// This should be symmetric. Returning `false` is always safe.
+    fn commutes_with(&self, other: &Self) -> bool;
+}
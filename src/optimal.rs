@@ -0,0 +1,174 @@
+use crate::chain::Chain;
+use crate::merge::Merge;
+
+/// Trait that tells how expensive an action is, for example its size when
+/// serialized or the time it takes to apply it.
+///
+/// Used by `Chain::compress_optimal` to pick the cheapest compressed chain.
+pub trait Cost {
+    /// Return the cost of this action.
+    fn cost(&self) -> u64;
+}
+
+/// Limits how much work `Chain::compress_optimal` does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchBudget {
+    /// The number of consecutive actions that are searched together.
+    /// The work per window grows with the cube of this number.
+    pub window: usize,
+    /// The maximum number of candidate chains that are compared in total.
+    /// Once exhausted, the remaining windows are compressed greedily.
+    pub max_evaluations: usize,
+}
+
+impl Default for SearchBudget {
+    fn default() -> Self {
+        Self {
+            window: 16,
+            max_evaluations: 100_000,
+        }
+    }
+}
+
+/// The outcome of `Chain::compress_optimal`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OptimalStats {
+    /// The total cost of the chain before compressing.
+    pub cost_before: u64,
+    /// The total cost of the chain after compressing.
+    pub cost_after: u64,
+    /// The number of candidate chains that were compared.
+    pub evaluations: usize,
+    /// `true` if the search budget ran out before every window was searched.
+    pub exhausted: bool,
+}
+
+impl<A: Sized + Clone> Chain<A> {
+    /// Compress the chain to the cheapest equivalent chain that can be found.
+    ///
+    /// `compress` merges greedily from left to right, so the result depends
+    /// on the order in which merges happen. This searches all the ways to
+    /// group the actions of each window of `budget.window` actions, and for
+    /// each group whether merging it makes the chain cheaper according to
+    /// `Cost`. The windows are then joined, and compressed once more if
+    /// that is cheaper.
+    ///
+    /// The result is never more expensive than the original chain or the
+    /// result of `compress_fully`.
+    pub fn compress_optimal(&mut self, budget: SearchBudget) -> OptimalStats
+    where
+        A: Merge + Cost,
+    {
+        let mut stats = OptimalStats {
+            cost_before: total_cost(self.actions()),
+            cost_after: 0,
+            evaluations: 0,
+            exhausted: false,
+        };
+
+        let mut greedy = self.clone();
+        greedy.compress_fully();
+
+        let mut searched: Vec<A> = Vec::with_capacity(self.len());
+        for window in self.actions().chunks(budget.window.max(1)) {
+            match search_window(window, budget.max_evaluations, &mut stats.evaluations) {
+                Some(best) => searched.extend(best),
+                None => {
+                    stats.exhausted = true;
+                    searched.extend(compressed(window.to_vec()));
+                }
+            }
+        }
+
+        // Merges across the borders of the windows are found by compressing
+        // once more, but only if that is cheaper.
+        let joined = compressed(searched.clone());
+        let searched = if total_cost(&joined) <= total_cost(&searched) {
+            joined
+        } else {
+            searched
+        };
+
+        // Keep the cheapest of the original, the greedy and the searched chain.
+        let greedy_cost = total_cost(greedy.actions());
+        let searched_cost = total_cost(&searched);
+        if searched_cost < greedy_cost && searched_cost < stats.cost_before {
+            *self = searched.into();
+        } else if greedy_cost < stats.cost_before {
+            *self = greedy;
+        }
+
+        stats.cost_after = total_cost(self.actions());
+        stats
+    }
+}
+
+fn total_cost<A: Cost>(actions: &[A]) -> u64 {
+    actions
+        .iter()
+        .fold(0, |sum, action| sum.saturating_add(action.cost()))
+}
+
+/// Return `actions` after compressing them fully.
+fn compressed<A: Clone + Merge>(actions: Vec<A>) -> Vec<A> {
+    let mut chain: Chain<A> = actions.into();
+    chain.compress_fully();
+    chain.into()
+}
+
+/// Find the cheapest chain equivalent to `window` by dynamic programming over
+/// every range of the window.
+///
+/// The cheapest chain for a range is the cheapest of, for every split point,
+/// the cheapest chains of both halves either placed after each other or
+/// compressed together.
+///
+/// # Return
+/// `None` if `max_evaluations` was reached.
+fn search_window<A>(window: &[A], max_evaluations: usize, evaluations: &mut usize) -> Option<Vec<A>>
+where
+    A: Clone + Merge + Cost,
+{
+    let n = window.len();
+    if n == 0 {
+        return Some(Vec::new());
+    }
+
+    // best[i][j - i - 1] is the cheapest chain for window[i..j].
+    let mut best: Vec<Vec<Vec<A>>> = (0..n).map(|i| vec![vec![window[i].clone()]]).collect();
+
+    for length in 2..=n {
+        for i in 0..=n - length {
+            let j = i + length;
+            let mut cheapest: Option<(u64, Vec<A>)> = None;
+
+            for k in i + 1..j {
+                let mut joined = best[i][k - i - 1].clone();
+                joined.extend(best[k][j - k - 1].iter().cloned());
+
+                for candidate in [compressed(joined.clone()), joined] {
+                    *evaluations += 1;
+                    if *evaluations > max_evaluations {
+                        return None;
+                    }
+
+                    let cost = total_cost(&candidate);
+                    let better = match cheapest {
+                        None => true,
+                        Some((cheapest_cost, ref cheapest)) => {
+                            cost < cheapest_cost
+                                || (cost == cheapest_cost && candidate.len() < cheapest.len())
+                        }
+                    };
+                    if better {
+                        cheapest = Some((cost, candidate));
+                    }
+                }
+            }
+
+            best[i].push(cheapest.unwrap().1);
+        }
+    }
+
+    best.swap_remove(0).pop()
+}
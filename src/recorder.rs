@@ -0,0 +1,388 @@
+use crate::chain::Chain;
+use crate::codec::{invalid_data, Codec};
+use crate::error::LoadError;
+use crate::format::FormatHeader;
+use crate::integrity::{Linking, RecordReader, RecordWriter};
+use crate::state::State;
+use crate::store::{HistoryStore, MemoryStore};
+use crate::timeline::{Timeline, TimelineError, TimelineOp};
+
+use std::fmt;
+use std::io::{self, Read, Write};
+
+const RECORD_OP: u8 = 0;
+const RECORD_END: u8 = 1;
+
+/// The outcome of an operation on a `Timeline`, without the details of
+/// the error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpOutcome {
+    /// The operation succeeded.
+    Succeeded,
+    /// `TimelineError::NothingToUndo`
+    NothingToUndo,
+    /// `TimelineError::NothingToRedo`
+    NothingToRedo,
+    /// `TimelineError::ApplyError`
+    ApplyFailed,
+    /// `TimelineError::Storage`
+    StorageFailed,
+    /// `TimelineError::Nondeterministic`
+    Nondeterministic,
+}
+
+impl OpOutcome {
+    /// Return the outcome of `result`, which comes from `Timeline::perform`.
+    pub(crate) fn of<E>(result: &Result<(), TimelineError<E>>) -> Self {
+        match result {
+            Ok(()) => OpOutcome::Succeeded,
+            Err(TimelineError::NothingToUndo) => OpOutcome::NothingToUndo,
+            Err(TimelineError::NothingToRedo) => OpOutcome::NothingToRedo,
+            Err(TimelineError::ApplyError(_)) => OpOutcome::ApplyFailed,
+            Err(TimelineError::Storage(_)) => OpOutcome::StorageFailed,
+            Err(TimelineError::Nondeterministic) => OpOutcome::Nondeterministic,
+            Err(TimelineError::InvalidRange { .. }) => {
+                unreachable!("only `Timeline::history_between` takes a range")
+            }
+        }
+    }
+}
+
+impl Codec for OpOutcome {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let tag: u8 = match self {
+            OpOutcome::Succeeded => 0,
+            OpOutcome::NothingToUndo => 1,
+            OpOutcome::NothingToRedo => 2,
+            OpOutcome::ApplyFailed => 3,
+            OpOutcome::StorageFailed => 4,
+            OpOutcome::Nondeterministic => 5,
+        };
+        tag.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::decode(reader)? {
+            0 => Ok(OpOutcome::Succeeded),
+            1 => Ok(OpOutcome::NothingToUndo),
+            2 => Ok(OpOutcome::NothingToRedo),
+            3 => Ok(OpOutcome::ApplyFailed),
+            4 => Ok(OpOutcome::StorageFailed),
+            5 => Ok(OpOutcome::Nondeterministic),
+            _ => Err(invalid_data("invalid operation outcome")),
+        }
+    }
+}
+
+/// An operation of a recorded session and its outcome.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedOp<A: Clone> {
+    /// The operation.
+    pub op: TimelineOp<A>,
+    /// The outcome of the operation when it was recorded.
+    pub outcome: OpOutcome,
+}
+
+/// Wraps around a `Timeline` and records every operation on it, so that a
+/// session can be replayed later, for example to reproduce a bug report.
+pub struct SessionRecorder<S: State + Clone, H: HistoryStore<S> = MemoryStore<S>>
+where
+    S::Action: Clone,
+{
+    timeline: Timeline<S, H>,
+    initial: S,
+    ops: Vec<RecordedOp<S::Action>>,
+}
+
+impl<S: State + Clone> SessionRecorder<S>
+where
+    S::Action: Clone,
+{
+    /// Create a new `SessionRecorder` with a new `Timeline` that wraps
+    /// around `state`.
+    pub fn new(state: S) -> Self {
+        Self::wrap(Timeline::new(state))
+    }
+}
+
+impl<S: State + Clone, H: HistoryStore<S>> SessionRecorder<S, H>
+where
+    S::Action: Clone,
+{
+    /// Create a new `SessionRecorder` that records the operations on
+    /// `timeline`, for example one that keeps its history in a `FileStore`.
+    ///
+    /// The recording starts at the current state of `timeline`. Its history
+    /// is not recorded, so undoing actions that were applied before is
+    /// recorded, but replays as `OpOutcome::NothingToUndo`.
+    pub fn wrap(timeline: Timeline<S, H>) -> Self {
+        Self {
+            initial: timeline.current_state().clone(),
+            timeline,
+            ops: Vec::new(),
+        }
+    }
+
+    /// Perform `op` on the timeline and record it.
+    pub fn perform(&mut self, op: TimelineOp<S::Action>) -> Result<(), TimelineError<S::Error>> {
+        let result = self.timeline.perform(&op);
+        self.ops.push(RecordedOp {
+            op,
+            outcome: OpOutcome::of(&result),
+        });
+        result
+    }
+
+    /// Apply an action and record it. See `Timeline::apply`.
+    pub fn apply(&mut self, action: S::Action) -> Result<(), TimelineError<S::Error>> {
+        self.perform(TimelineOp::Apply(action))
+    }
+
+    /// Apply a chain of actions and record it. See `Timeline::apply_chain`.
+    pub fn apply_chain(&mut self, chain: Chain<S::Action>) -> Result<(), TimelineError<S::Error>> {
+        self.perform(TimelineOp::ApplyChain(chain))
+    }
+
+    /// Undo and record it. See `Timeline::undo`.
+    pub fn undo(&mut self) -> Result<(), TimelineError<S::Error>> {
+        self.perform(TimelineOp::Undo)
+    }
+
+    /// Redo and record it. See `Timeline::redo`.
+    pub fn redo(&mut self) -> Result<(), TimelineError<S::Error>> {
+        self.perform(TimelineOp::Redo)
+    }
+
+    /// Get a reference to the recorded timeline.
+    pub fn timeline(&self) -> &Timeline<S, H> {
+        &self.timeline
+    }
+
+    /// Get a reference to the current state.
+    pub fn current_state(&self) -> &S {
+        self.timeline.current_state()
+    }
+
+    /// Return the session recorded so far.
+    pub fn recording(&self) -> Recording<S> {
+        Recording {
+            initial: self.initial.clone(),
+            ops: self.ops.clone(),
+            final_state: self.timeline.current_state().clone(),
+        }
+    }
+
+    /// Write the session recorded so far to `writer`, in the format read by
+    /// `Recording::read_from`.
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()>
+    where
+        S: Codec,
+        S::Action: Codec,
+    {
+        self.recording().write_to(writer)
+    }
+
+    /// Stop recording and return the timeline.
+    pub fn into_timeline(self) -> Timeline<S, H> {
+        self.timeline
+    }
+}
+
+/// A recorded session: a state, the operations performed on a `Timeline`
+/// that wrapped around it, and the state they led to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording<S: State>
+where
+    S::Action: Clone,
+{
+    /// The state the session started at.
+    pub initial: S,
+    /// The operations of the session, in order.
+    pub ops: Vec<RecordedOp<S::Action>>,
+    /// The state at the end of the session.
+    pub final_state: S,
+}
+
+impl<S: State + Clone> Recording<S>
+where
+    S::Action: Clone,
+{
+    /// Write the recording to `writer`.
+    ///
+    /// The data starts with a `FormatHeader`, followed by hash-chained
+    /// records: the initial state, one record per operation and a last
+    /// record with the final state, so that a cut off recording is refused.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()>
+    where
+        S: Codec,
+        S::Action: Codec,
+    {
+        let header = FormatHeader {
+            magic: FormatHeader::RECORDING_MAGIC,
+            version: FormatHeader::RECORDING_VERSION,
+        };
+        header.write(&mut writer)?;
+
+        let mut records = RecordWriter::new(writer, Linking::Chained);
+        let mut payload = Vec::new();
+
+        self.initial.encode(&mut payload)?;
+        records.write_record(&payload)?;
+
+        for recorded in &self.ops {
+            payload.clear();
+            RECORD_OP.encode(&mut payload)?;
+            recorded.op.encode(&mut payload)?;
+            recorded.outcome.encode(&mut payload)?;
+            records.write_record(&payload)?;
+        }
+
+        payload.clear();
+        RECORD_END.encode(&mut payload)?;
+        self.final_state.encode(&mut payload)?;
+        records.write_record(&payload)?;
+
+        records.flush()
+    }
+
+    /// Read a recording that was written by `write_to`.
+    ///
+    /// # Return
+    /// The recording, or `LoadError::Corrupt` if the data was damaged,
+    /// edited or cut off.
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, LoadError>
+    where
+        S: Codec,
+        S::Action: Codec,
+    {
+        // Older versions of the format are migrated here once they exist.
+        match FormatHeader::expect(&mut reader, FormatHeader::RECORDING_MAGIC)? {
+            FormatHeader::RECORDING_VERSION => {}
+            version => return Err(LoadError::UnsupportedVersion(version)),
+        }
+
+        let mut records = RecordReader::new(reader, Linking::Chained);
+        let initial = S::decode(&mut &records.read_expected()?[..])?;
+
+        let mut ops = Vec::new();
+        loop {
+            let record = records.read_expected()?;
+            let mut record = &record[..];
+            match u8::decode(&mut record)? {
+                RECORD_OP => ops.push(RecordedOp {
+                    op: TimelineOp::decode(&mut record)?,
+                    outcome: OpOutcome::decode(&mut record)?,
+                }),
+                RECORD_END => {
+                    let final_state = S::decode(&mut record)?;
+                    records.expect_end()?;
+                    return Ok(Self {
+                        initial,
+                        ops,
+                        final_state,
+                    });
+                }
+                _ => return Err(invalid_data("unknown recording record").into()),
+            }
+        }
+    }
+
+    /// Perform the recorded operations on a new `Timeline` that wraps
+    /// around the initial state.
+    ///
+    /// # Return
+    /// The timeline at the end of the session, or the first difference
+    /// with the recording.
+    pub fn replay(&self) -> Result<Timeline<S>, ReplayMismatch<S>>
+    where
+        S: PartialEq,
+    {
+        let mut timeline = Timeline::new(self.initial.clone());
+
+        for (step, recorded) in self.ops.iter().enumerate() {
+            let actual = OpOutcome::of(&timeline.perform(&recorded.op));
+            if actual != recorded.outcome {
+                return Err(ReplayMismatch::Outcome {
+                    step,
+                    expected: recorded.outcome,
+                    actual,
+                });
+            }
+        }
+
+        if *timeline.current_state() != self.final_state {
+            return Err(ReplayMismatch::FinalState {
+                expected: self.final_state.clone(),
+                actual: timeline.current_state().clone(),
+            });
+        }
+
+        Ok(timeline)
+    }
+
+    /// Replay the recording and panic if it does not end the same way as
+    /// when it was recorded.
+    ///
+    /// Meant to be used in a test that reproduces a recorded session.
+    pub fn assert_replays(&self)
+    where
+        S: PartialEq + fmt::Debug,
+        S::Action: fmt::Debug,
+    {
+        if let Err(mismatch) = self.replay() {
+            let mut trace = String::new();
+            for (step, recorded) in self.ops.iter().enumerate() {
+                trace.push_str(&format!(
+                    "  {}: {:?} -> {:?}\n",
+                    step, recorded.op, recorded.outcome
+                ));
+            }
+            panic!(
+                "The recording did not replay: {}\nRecorded session, starting at {:?}:\n{}",
+                mismatch, self.initial, trace
+            );
+        }
+    }
+}
+
+/// The first difference between a replay and its `Recording`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplayMismatch<S> {
+    /// The operation at `step` had another outcome.
+    Outcome {
+        /// The index of the operation.
+        step: usize,
+        /// The recorded outcome.
+        expected: OpOutcome,
+        /// The outcome of the replay.
+        actual: OpOutcome,
+    },
+    /// Every operation had the same outcome, but the final state differs.
+    FinalState {
+        /// The recorded final state.
+        expected: S,
+        /// The final state of the replay.
+        actual: S,
+    },
+}
+
+impl<S: fmt::Debug> fmt::Display for ReplayMismatch<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayMismatch::Outcome {
+                step,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "operation {} was recorded as {:?}, but replayed as {:?}",
+                step, expected, actual
+            ),
+            ReplayMismatch::FinalState { expected, actual } => write!(
+                f,
+                "the session was recorded to end at {:?}, but replayed to end at {:?}",
+                expected, actual
+            ),
+        }
+    }
+}
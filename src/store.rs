@@ -0,0 +1,341 @@
+use crate::codec::{invalid_data, Codec};
+use crate::integrity::{Linking, RecordReader, RecordWriter};
+use crate::state::State;
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// What the `Timeline` stores to be able to undo an action.
+pub enum Breadcrumb<S: State> {
+    /// A full copy of the state from before the action was applied.
+    FullCopy(Box<S>),
+    /// The inverse of the action.
+    Action(S::Action),
+}
+
+impl<S: State + Clone> Clone for Breadcrumb<S>
+where
+    S::Action: Clone,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Breadcrumb::FullCopy(state) => Breadcrumb::FullCopy(state.clone()),
+            Breadcrumb::Action(action) => Breadcrumb::Action(action.clone()),
+        }
+    }
+}
+
+impl<S: State + Codec> Codec for Breadcrumb<S>
+where
+    S::Action: Codec,
+{
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Breadcrumb::FullCopy(state) => {
+                0u8.encode(writer)?;
+                state.encode(writer)
+            }
+            Breadcrumb::Action(action) => {
+                1u8.encode(writer)?;
+                action.encode(writer)
+            }
+        }
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::decode(reader)? {
+            0 => Ok(Breadcrumb::FullCopy(Box::decode(reader)?)),
+            1 => Ok(Breadcrumb::Action(S::Action::decode(reader)?)),
+            _ => Err(invalid_data("invalid breadcrumb tag")),
+        }
+    }
+}
+
+/// One step in the history of a `Timeline`.
+pub struct Entry<S: State> {
+    /// The action that was applied.
+    pub action: S::Action,
+    /// What is needed to undo the action.
+    pub breadcrumb: Breadcrumb<S>,
+}
+
+impl<S: State + Clone> Clone for Entry<S>
+where
+    S::Action: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            action: self.action.clone(),
+            breadcrumb: self.breadcrumb.clone(),
+        }
+    }
+}
+
+impl<S: State + Codec> Codec for Entry<S>
+where
+    S::Action: Codec,
+{
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.action.encode(writer)?;
+        self.breadcrumb.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(Self {
+            action: S::Action::decode(reader)?,
+            breadcrumb: Breadcrumb::decode(reader)?,
+        })
+    }
+}
+
+/// Storage for the history of a `Timeline`.
+///
+/// The entries are indexed from the oldest (0) to the newest (`len() - 1`).
+pub trait HistoryStore<S: State> {
+    /// Return the number of entries in the store.
+    fn len(&self) -> usize;
+
+    /// Return `true` if the store contains no entries.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add an entry after the newest entry.
+    fn push(&mut self, entry: Entry<S>) -> io::Result<()>;
+
+    /// Return a copy of the entry at `index`.
+    ///
+    /// `index` is always smaller than `len()`.
+    fn get(&self, index: usize) -> io::Result<Entry<S>>;
+
+    /// Return a copy of the action of the entry at `index`.
+    ///
+    /// Stores that can copy the action without the breadcrumb should
+    /// override this, so that redoing does not copy a full state.
+    fn get_action(&self, index: usize) -> io::Result<S::Action> {
+        self.get(index).map(|entry| entry.action)
+    }
+
+    /// Remove every entry from `len` onwards.
+    fn truncate(&mut self, len: usize) -> io::Result<()>;
+}
+
+/// Keeps the whole history in memory. This is the default store of a `Timeline`.
+pub struct MemoryStore<S: State> {
+    entries: Vec<Entry<S>>,
+}
+
+impl<S: State> MemoryStore<S> {
+    /// Create a new empty `MemoryStore`.
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<S: State> Default for MemoryStore<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: State + Clone> HistoryStore<S> for MemoryStore<S>
+where
+    S::Action: Clone,
+{
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn push(&mut self, entry: Entry<S>) -> io::Result<()> {
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> io::Result<Entry<S>> {
+        Ok(self.entries[index].clone())
+    }
+
+    fn get_action(&self, index: usize) -> io::Result<S::Action> {
+        Ok(self.entries[index].action.clone())
+    }
+
+    fn truncate(&mut self, len: usize) -> io::Result<()> {
+        self.entries.truncate(len);
+        Ok(())
+    }
+}
+
+/// Keeps the whole history in a file.
+///
+/// Every entry is written as a checksummed record, so that damage to the file
+/// is reported as an error of kind `InvalidData` instead of being replayed.
+/// Only the offsets of the entries are kept in memory.
+pub struct FileStore<S: State> {
+    file: File,
+    offsets: Vec<u64>,
+    end: u64,
+    state: PhantomData<S>,
+}
+
+impl<S: State> FileStore<S> {
+    /// Create a new `FileStore` at `path`.
+    ///
+    /// If the file already exists, it is truncated.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        Ok(Self::from_file(file))
+    }
+
+    /// Create a new `FileStore` that writes to `file`.
+    ///
+    /// The file has to be opened for both reading and writing.
+    /// Any data already in the file is overwritten.
+    pub fn from_file(file: File) -> Self {
+        Self {
+            file,
+            offsets: Vec::new(),
+            end: 0,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<S: State + Codec> HistoryStore<S> for FileStore<S>
+where
+    S::Action: Codec,
+{
+    fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    fn push(&mut self, entry: Entry<S>) -> io::Result<()> {
+        let mut payload = Vec::new();
+        entry.encode(&mut payload)?;
+
+        self.file.seek(SeekFrom::Start(self.end))?;
+        let mut writer = RecordWriter::new(&mut self.file, Linking::Unlinked);
+        writer.write_record(&payload)?;
+
+        self.offsets.push(self.end);
+        self.end = self.file.stream_position()?;
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> io::Result<Entry<S>> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(self.offsets[index]))?;
+
+        let payload = RecordReader::new(file, Linking::Unlinked).read_expected()?;
+
+        Entry::decode(&mut &payload[..])
+    }
+
+    fn get_action(&self, index: usize) -> io::Result<S::Action> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(self.offsets[index]))?;
+
+        let payload = RecordReader::new(file, Linking::Unlinked).read_expected()?;
+
+        // The action is encoded first, the breadcrumb is never decoded.
+        S::Action::decode(&mut &payload[..])
+    }
+
+    fn truncate(&mut self, len: usize) -> io::Result<()> {
+        if len < self.offsets.len() {
+            self.end = self.offsets[len];
+            self.offsets.truncate(len);
+            self.file.set_len(self.end)?;
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the newest entries in memory and moves older entries to a file.
+///
+/// Old breadcrumbs, which may be large full copies of the state, are only
+/// read back when the user undoes that far.
+pub struct SpillStore<S: State> {
+    recent: VecDeque<Entry<S>>,
+    spilled: FileStore<S>,
+    capacity: usize,
+}
+
+impl<S: State> SpillStore<S> {
+    /// Create a new `SpillStore` that keeps at most `capacity` entries in
+    /// memory and spills older entries to a new file at `path`.
+    pub fn create<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<Self> {
+        Ok(Self::from_file_store(FileStore::create(path)?, capacity))
+    }
+
+    /// Create a new `SpillStore` that spills to `spilled`.
+    pub fn from_file_store(spilled: FileStore<S>, capacity: usize) -> Self {
+        Self {
+            recent: VecDeque::new(),
+            spilled,
+            capacity,
+        }
+    }
+}
+
+impl<S: State + Clone + Codec> HistoryStore<S> for SpillStore<S>
+where
+    S::Action: Clone + Codec,
+{
+    fn len(&self) -> usize {
+        self.spilled.len() + self.recent.len()
+    }
+
+    fn push(&mut self, entry: Entry<S>) -> io::Result<()> {
+        self.recent.push_back(entry);
+
+        while self.recent.len() > self.capacity {
+            // Spill the oldest entry that is still in memory.
+            let oldest = self.recent.pop_front().unwrap();
+            self.spilled.push(oldest)?;
+        }
+        Ok(())
+    }
+
+    fn get(&self, index: usize) -> io::Result<Entry<S>> {
+        let spilled = self.spilled.len();
+
+        if index < spilled {
+            self.spilled.get(index)
+        } else {
+            Ok(self.recent[index - spilled].clone())
+        }
+    }
+
+    fn get_action(&self, index: usize) -> io::Result<S::Action> {
+        let spilled = self.spilled.len();
+
+        if index < spilled {
+            self.spilled.get_action(index)
+        } else {
+            Ok(self.recent[index - spilled].action.clone())
+        }
+    }
+
+    fn truncate(&mut self, len: usize) -> io::Result<()> {
+        let spilled = self.spilled.len();
+
+        if len <= spilled {
+            self.recent.clear();
+            self.spilled.truncate(len)
+        } else {
+            self.recent.truncate(len - spilled);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::chain::Chain;
+use crate::equivalence::Outcome;
+use crate::merge::{Merge, MergeResult};
+use crate::state::State;
+use crate::testing::laws::{Law, Violation};
+
+/// The number of generated states every case is checked on, besides the
+/// state its actions were generated for.
+///
+/// A `MergeResult` does not depend on the state, so it should hold for
+/// every state the actions can be applied to.
+const EXTRA_STATES: usize = 3;
+
+/// Generated actions that can be applied one after the other, and the
+/// states to check them on.
+struct Case<S: State> {
+    /// The first state is the state the actions were generated for.
+    states: Vec<S>,
+    actions: Vec<S::Action>,
+}
+
+/// Generate `len` actions starting at a generated state.
+///
+/// # Return
+/// `None` if a generated action can not be applied.
+fn gen_case<S, GS, GA>(len: usize, gen_state: &mut GS, gen_action: &mut GA) -> Option<Case<S>>
+where
+    S: State + Clone,
+    GS: FnMut() -> S,
+    GA: FnMut(&S) -> S::Action,
+{
+    let state = gen_state();
+    let mut scratch = state.clone();
+    let mut actions = Vec::with_capacity(len);
+    for _ in 0..len {
+        let action = gen_action(&scratch);
+        scratch.apply(&action).ok()?;
+        actions.push(action);
+    }
+
+    let mut states = vec![state];
+    states.extend((0..EXTRA_STATES).map(|_| gen_state()));
+    Some(Case { states, actions })
+}
+
+/// Check that `right` has the same effect as `left` on every state that
+/// `left` can be applied to.
+fn compare<S>(
+    law: Law,
+    case: usize,
+    states: Vec<S>,
+    left: Chain<S::Action>,
+    right: Chain<S::Action>,
+) -> Result<(), Violation<S>>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone,
+{
+    for state in states {
+        let expected = match Outcome::of(&left, &state) {
+            Outcome::Applied(expected) => expected,
+            Outcome::Failed { .. } => continue,
+        };
+
+        let actual = Outcome::of(&right, &state);
+        if !actual.matches(&Outcome::Applied(expected.clone())) {
+            return Err(Violation {
+                law,
+                case,
+                state,
+                left,
+                right,
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Return the actions that merging `action` into `previous` gives, or `None`
+/// if they are unmergable.
+fn merged<A: Clone + Merge>(previous: &A, action: &A) -> Option<Vec<A>> {
+    match action.merge(previous) {
+        MergeResult::Unmergable => None,
+        MergeResult::CancelsOut => Some(Vec::new()),
+        MergeResult::OverwritesPrevious | MergeResult::OverwritesState => {
+            Some(vec![action.clone()])
+        }
+        MergeResult::Merged(merged) => Some(vec![merged]),
+        MergeResult::Replace(actions) => Some(actions),
+    }
+}
+
+/// Return the actions that merging `action` into `previous` gives, or both
+/// actions if they are unmergable.
+fn merged_or_both<A: Clone + Merge>(previous: &A, action: &A) -> Vec<A> {
+    merged(previous, action).unwrap_or_else(|| vec![previous.clone(), action.clone()])
+}
+
+/// Check that merging three actions has the same effect however the merges
+/// are bracketed, for `cases` generated states and actions.
+///
+/// For actions `a`, `b` and `c`, `merge(merge(a, b), c)` and
+/// `merge(a, merge(b, c))` should have the same effect on every state. A
+/// merge that does not give a single action is not merged any further.
+///
+/// `left` and `right` of a violation hold the two bracketings.
+pub fn check_associativity<S, GS, GA>(
+    cases: usize,
+    mut gen_state: GS,
+    mut gen_action: GA,
+) -> Result<(), Violation<S>>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone + Merge,
+    GS: FnMut() -> S,
+    GA: FnMut(&S) -> S::Action,
+{
+    for case in 0..cases {
+        let Case { states, actions } = match gen_case(3, &mut gen_state, &mut gen_action) {
+            Some(generated) => generated,
+            None => continue,
+        };
+        let (a, b, c) = (&actions[0], &actions[1], &actions[2]);
+
+        // Only a merge into a single action can be merged again.
+        let left = match merged_or_both(a, b).as_slice() {
+            [ab] => merged_or_both(ab, c),
+            ab => ab.iter().chain(Some(c)).cloned().collect(),
+        };
+        let right = match merged_or_both(b, c).as_slice() {
+            [bc] => merged_or_both(a, bc),
+            bc => Some(a).into_iter().chain(bc).cloned().collect(),
+        };
+
+        compare(Law::Associativity, case, states, left.into(), right.into())?;
+    }
+
+    Ok(())
+}
+
+/// Check that two actions that cancel each other out have no effect at all,
+/// for `cases` generated states and actions.
+///
+/// The actions are applied to other generated states too, because they
+/// should cancel out on every state.
+pub fn check_cancels_out<S, GS, GA>(
+    cases: usize,
+    mut gen_state: GS,
+    mut gen_action: GA,
+) -> Result<(), Violation<S>>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone + Merge,
+    GS: FnMut() -> S,
+    GA: FnMut(&S) -> S::Action,
+{
+    for case in 0..cases {
+        let Case { states, actions } = match gen_case(2, &mut gen_state, &mut gen_action) {
+            Some(generated) => generated,
+            None => continue,
+        };
+
+        if let MergeResult::CancelsOut = actions[1].merge(&actions[0]) {
+            // Applying no actions gives back the state itself.
+            compare(Law::CancelsOut, case, states, Chain::new(), actions.into())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that an action that overwrites the previous action or the whole
+/// state really ignores what came before it, for `cases` generated states
+/// and actions.
+///
+/// With `OverwritesPrevious`, applying both actions should have the same
+/// effect as applying only the last. With `OverwritesState`, applying the
+/// last action should have the same effect as applying every action before
+/// it too, on any state.
+pub fn check_overwrites<S, GS, GA>(
+    cases: usize,
+    mut gen_state: GS,
+    mut gen_action: GA,
+) -> Result<(), Violation<S>>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone + Merge,
+    GS: FnMut() -> S,
+    GA: FnMut(&S) -> S::Action,
+{
+    for case in 0..cases {
+        let Case { states, actions } = match gen_case(3, &mut gen_state, &mut gen_action) {
+            Some(generated) => generated,
+            None => continue,
+        };
+
+        let last: Chain<S::Action> = vec![actions[2].clone()].into();
+        match actions[2].merge(&actions[1]) {
+            MergeResult::OverwritesPrevious => compare(
+                Law::OverwritesPrevious,
+                case,
+                states,
+                actions[1..].to_vec().into(),
+                last,
+            )?,
+            MergeResult::OverwritesState => {
+                compare(Law::OverwritesState, case, states, actions.into(), last)?
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
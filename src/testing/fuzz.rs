@@ -0,0 +1,180 @@
+use crate::chain::{Chain, FullCopyPolicy};
+use crate::compressor::Compressor;
+use crate::equivalence::Outcome;
+use crate::testing::model::run_timeline_model;
+use crate::testing::register::{Register, RegisterAction};
+use crate::timeline::TimelineOp;
+
+/// The maximum number of actions in a chain applied by `fuzz_timeline`.
+const MAX_CHAIN_LEN: u8 = 8;
+
+/// Reads numbers from the bytes given by a fuzzer.
+///
+/// Once the bytes run out, every number is 0.
+struct Bytes<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Bytes<'a> {
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn byte(&mut self) -> u8 {
+        match self.data.split_first() {
+            Some((&byte, rest)) => {
+                self.data = rest;
+                byte
+            }
+            None => 0,
+        }
+    }
+
+    fn number(&mut self, wide: bool) -> i64 {
+        if !wide {
+            return i64::from(self.byte() as i8);
+        }
+
+        let mut bytes = [0; 8];
+        for byte in &mut bytes {
+            *byte = self.byte();
+        }
+        i64::from_le_bytes(bytes)
+    }
+
+    fn register(&mut self) -> Register {
+        let wide = self.byte() & 1 == 1;
+        Register(self.number(wide))
+    }
+
+    /// The low bits of the tag select the action, the high bit selects a
+    /// number of 8 bytes instead of 1 byte.
+    fn action(&mut self) -> RegisterAction {
+        let tag = self.byte();
+        let n = self.number(tag & 0x80 != 0);
+        match tag % 6 {
+            0 => RegisterAction::Add(n),
+            1 => RegisterAction::Mul(n),
+            2 => RegisterAction::Set(n),
+            3 => RegisterAction::Neg,
+            4 => RegisterAction::Xor(n),
+            _ => RegisterAction::Div(n),
+        }
+    }
+
+    fn chain(&mut self, len: usize) -> Chain<RegisterAction> {
+        (0..len).map(|_| self.action()).collect()
+    }
+}
+
+/// Fuzz the compression of chains with arbitrary bytes.
+///
+/// The bytes are read as a `Register` and a chain of actions. The chain is
+/// compressed with `compress`, `compress_fully`, `compress_commuting` and a
+/// `Compressor` with a window, and its inverse is computed. Panics if any of
+/// them panics, if applying the result differs from applying the original
+/// chain, or if a `Compressor` without a window differs from `compress`.
+pub fn fuzz_compress(data: &[u8]) {
+    let mut bytes = Bytes { data };
+    let register = bytes.register();
+    let window = usize::from(bytes.byte() % 16);
+    let mut chain = Chain::new();
+    while !bytes.is_empty() {
+        chain.push(bytes.action());
+    }
+
+    // A chain that fails may be compressed into a chain that does not.
+    let expected = match Outcome::of(&chain, &register) {
+        Outcome::Applied(expected) => expected,
+        Outcome::Failed { .. } => return,
+    };
+
+    let check = |name: &str, compressed: &Chain<RegisterAction>| {
+        assert!(
+            compressed.len() <= chain.len(),
+            "{} made {:?} longer: {:?}",
+            name,
+            chain,
+            compressed
+        );
+        assert!(
+            Outcome::of(compressed, &register).matches(&Outcome::Applied(expected)),
+            "{} changed the effect of {:?} on {:?}: {:?}",
+            name,
+            chain,
+            register,
+            compressed
+        );
+    };
+
+    let mut compressed = chain.clone();
+    compressed.compress();
+    check("compress", &compressed);
+
+    let mut compressor = Compressor::new();
+    for action in chain.iter() {
+        assert!(compressor.push(*action).is_empty());
+    }
+    assert_eq!(
+        compressor.finish(),
+        compressed.actions(),
+        "Compressor differs from compress for {:?}",
+        chain
+    );
+
+    let mut compressed = chain.clone();
+    let stats = compressed.compress_fully();
+    assert_eq!(stats.len_after, compressed.len());
+    check("compress_fully", &compressed);
+
+    let mut compressed = chain.clone();
+    compressed.compress_commuting();
+    check("compress_commuting", &compressed);
+
+    let mut compressor = Compressor::with_window(window);
+    let mut streamed: Vec<RegisterAction> = Vec::new();
+    for action in chain.iter() {
+        streamed.extend(compressor.push(*action));
+    }
+    streamed.extend(compressor.finish());
+    check("Compressor::with_window", &streamed.into());
+
+    let restore = FullCopyPolicy::Restore(Box::new(|register: &Register| {
+        RegisterAction::Set(register.0)
+    }));
+    let mut round_trip = chain.clone();
+    round_trip.extend(&chain.inverse(&register, restore).unwrap());
+    assert!(
+        Outcome::of(&round_trip, &register).matches(&Outcome::Applied(register)),
+        "the inverse of {:?} does not undo it on {:?}",
+        chain,
+        register
+    );
+}
+
+/// Fuzz a `Timeline` with arbitrary bytes.
+///
+/// The bytes are read as a `Register` and operations on a `Timeline`: apply,
+/// undo, redo and apply_chain. Panics if the timeline panics, or if it
+/// differs from a `SnapshotModel` after any operation.
+pub fn fuzz_timeline(data: &[u8]) {
+    let mut bytes = Bytes { data };
+    let register = bytes.register();
+
+    let mut ops = Vec::new();
+    while !bytes.is_empty() {
+        ops.push(match bytes.byte() % 4 {
+            0 => TimelineOp::Apply(bytes.action()),
+            1 => TimelineOp::Undo,
+            2 => TimelineOp::Redo,
+            _ => {
+                let len = bytes.byte() % MAX_CHAIN_LEN + 1;
+                TimelineOp::ApplyChain(bytes.chain(usize::from(len)))
+            }
+        });
+    }
+
+    if let Err(mismatch) = run_timeline_model(register, ops) {
+        panic!("{}", mismatch);
+    }
+}
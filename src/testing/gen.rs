@@ -0,0 +1,168 @@
+use crate::chain::Chain;
+use crate::merge::Merge;
+use crate::state::State;
+use crate::testing::algebra::{check_associativity, check_cancels_out, check_overwrites};
+use crate::testing::laws::{
+    check_compress_preserves, check_inverse_law, check_merge_law, Violation,
+};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::cell::RefCell;
+
+/// The maximum length of the chains generated by `check_laws`.
+const MAX_CHAIN_LEN: usize = 16;
+
+/// Trait that generates random states.
+pub trait GenState: State + Sized {
+    /// Generate a random state.
+    fn gen_state<R: Rng + ?Sized>(rng: &mut R) -> Self;
+}
+
+/// Trait that generates random actions for a state.
+pub trait GenAction<S: State<Action = Self>>: Sized {
+    /// Generate a random action that can be applied to `state`.
+    fn gen_action<R: Rng + ?Sized>(state: &S, rng: &mut R) -> Self;
+}
+
+/// Trait that returns smaller versions of a value, so that a failing case
+/// can be made as small as possible.
+///
+/// The law checkers shrink the states and actions of a failing case with it.
+/// Types that do not override `shrink` are reported as generated.
+pub trait Shrink: Sized {
+    /// Return values that are smaller than `self`, the most promising first.
+    ///
+    /// Every value should be smaller by some measure, so that shrinking ends.
+    fn shrink(&self) -> Vec<Self> {
+        Vec::new()
+    }
+}
+
+impl<A: Shrink + Clone, B: Shrink + Clone> Shrink for (A, B) {
+    fn shrink(&self) -> Vec<Self> {
+        let (a, b) = self;
+        let mut smaller: Vec<Self> = a.shrink().into_iter().map(|a| (a, b.clone())).collect();
+        smaller.extend(b.shrink().into_iter().map(|b| (a.clone(), b)));
+        smaller
+    }
+}
+
+impl<A, B, C> Shrink for (A, B, C)
+where
+    A: Shrink + Clone,
+    B: Shrink + Clone,
+    C: Shrink + Clone,
+{
+    fn shrink(&self) -> Vec<Self> {
+        let (a, b, c) = self;
+        let mut smaller: Vec<Self> = a
+            .shrink()
+            .into_iter()
+            .map(|a| (a, b.clone(), c.clone()))
+            .collect();
+        smaller.extend(b.shrink().into_iter().map(|b| (a.clone(), b, c.clone())));
+        smaller.extend(c.shrink().into_iter().map(|c| (a.clone(), b.clone(), c)));
+        smaller
+    }
+}
+
+/// Return a random number generator that always generates the same numbers
+/// for the same `seed`.
+///
+/// The numbers only stay the same for the same version of `rand`.
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// Generate a chain of up to `len` actions that can be applied to `state`
+/// one after the other.
+///
+/// Every action is generated for the state after applying the actions
+/// before it. The chain ends early if a generated action fails.
+pub fn gen_chain<S, R>(state: &S, len: usize, rng: &mut R) -> Chain<S::Action>
+where
+    S: State + Clone,
+    S::Action: GenAction<S> + Clone,
+    R: Rng + ?Sized,
+{
+    let mut scratch = state.clone();
+    let mut chain = Chain::with_capacity(len);
+    for _ in 0..len {
+        let action = S::Action::gen_action(&scratch, rng);
+        if scratch.apply(&action).is_err() {
+            break;
+        }
+        chain.push(action);
+    }
+    chain
+}
+
+/// Remove actions from `chain` one at a time, for as long as `fails`
+/// returns `true` for the smaller chain.
+///
+/// Removing an action can make it possible to remove an action before it,
+/// so this repeats until no action can be removed.
+pub fn shrink_chain<A, F>(mut chain: Chain<A>, mut fails: F) -> Chain<A>
+where
+    A: Clone,
+    F: FnMut(&Chain<A>) -> bool,
+{
+    loop {
+        let length_old = chain.len();
+
+        let mut i = 0;
+        while i < chain.len() {
+            let mut candidate = chain.clone();
+            candidate.remove(i);
+            if fails(&candidate) {
+                chain = candidate;
+            } else {
+                i += 1;
+            }
+        }
+
+        if chain.len() == length_old {
+            return chain;
+        }
+    }
+}
+
+/// Replace `value` by one of its smaller values for as long as `fails`
+/// returns `true` for the smaller value.
+///
+/// Like `shrink_chain`, this repeats until none of the smaller values of the
+/// current value fail.
+pub fn shrink<T, F>(mut value: T, mut fails: F) -> T
+where
+    T: Shrink,
+    F: FnMut(&T) -> bool,
+{
+    while let Some(smaller) = value.shrink().into_iter().find(|smaller| fails(smaller)) {
+        value = smaller;
+    }
+    value
+}
+
+/// Run every checker of this module that tests a `State` or `Merge`
+/// implementation for `cases` cases each, with states and actions
+/// generated from `seed`.
+///
+/// Running it again with the same seed checks the exact same cases.
+pub fn check_laws<S>(seed: u64, cases: usize) -> Result<(), Violation<S>>
+where
+    S: GenState + Shrink + Clone + PartialEq,
+    S::Action: GenAction<S> + Merge + Shrink + Clone,
+{
+    let rng = RefCell::new(seeded_rng(seed));
+    let gen_state = || S::gen_state(&mut *rng.borrow_mut());
+    let gen_action = |state: &S| S::Action::gen_action(state, &mut *rng.borrow_mut());
+
+    check_inverse_law(cases, gen_state, gen_action)?;
+    check_merge_law(cases, gen_state, gen_action)?;
+    check_associativity(cases, gen_state, gen_action)?;
+    check_cancels_out(cases, gen_state, gen_action)?;
+    check_overwrites(cases, gen_state, gen_action)?;
+    check_compress_preserves(cases, MAX_CHAIN_LEN, gen_state, gen_action)
+}
@@ -0,0 +1,193 @@
+use crate::chain::Chain;
+use crate::merge::Merge;
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The environment variable that makes `check_golden` write the golden
+/// files instead of comparing with them, if it is set to anything but `0`.
+pub const UPDATE_GOLDEN_VAR: &str = "ACTIONS_UPDATE_GOLDEN";
+
+/// Why a golden file check failed.
+#[derive(Debug)]
+pub enum GoldenError {
+    /// The golden file does not exist.
+    Missing(PathBuf),
+    /// The golden file differs from the rendered text.
+    Mismatch {
+        /// The path of the golden file.
+        path: PathBuf,
+        /// The lines that differ: `-` for lines of the golden file, `+` for
+        /// lines of the rendered text.
+        diff: String,
+    },
+    /// Reading or writing the golden file failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Missing(path) => write!(
+                f,
+                "the golden file {} does not exist, set {}=1 to create it",
+                path.display(),
+                UPDATE_GOLDEN_VAR
+            ),
+            GoldenError::Mismatch { path, diff } => write!(
+                f,
+                "the output differs from the golden file {}, set {}=1 to update it if the change is intended:\n{}",
+                path.display(),
+                UPDATE_GOLDEN_VAR,
+                diff
+            ),
+            GoldenError::Io(e) => write!(f, "could not access the golden file: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for GoldenError {
+    fn from(e: io::Error) -> Self {
+        GoldenError::Io(e)
+    }
+}
+
+/// Render `chain` and the result of `Chain::compress` on it as text, one
+/// action per line, with the `Debug` format of the actions.
+///
+/// The text only changes if the compression or the `Debug` output changes.
+pub fn render_compression<A>(chain: &Chain<A>) -> String
+where
+    A: Merge + Clone + fmt::Debug,
+{
+    let mut compressed = chain.clone();
+    compressed.compress();
+
+    let mut text = format!("# {} actions\n", chain.len());
+    for action in chain.actions() {
+        text.push_str(&format!("{:?}\n", action));
+    }
+    text.push_str(&format!("# compressed to {} actions\n", compressed.len()));
+    for action in compressed.actions() {
+        text.push_str(&format!("{:?}\n", action));
+    }
+    text
+}
+
+/// Return the lines of `expected` and `actual` that differ, with a line of
+/// context around every change. Hunks that are not adjacent are separated
+/// by a line with `@@`.
+fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // The length of the longest common subsequence of old[i..] and new[j..].
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(('-', old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', new[j]));
+            j += 1;
+        }
+    }
+
+    let changed = |index: usize| matches!(lines.get(index), Some((tag, _)) if *tag != ' ');
+    let mut text = String::new();
+    let mut last_shown = None;
+    for (index, (tag, line)) in lines.iter().enumerate() {
+        if *tag != ' ' || changed(index + 1) || (index > 0 && changed(index - 1)) {
+            match last_shown {
+                Some(last) if last + 1 < index => text.push_str("@@\n"),
+                _ => {}
+            }
+            text.push_str(&format!("{} {}\n", tag, line));
+            last_shown = Some(index);
+        }
+    }
+    text
+}
+
+/// Write `actual` to the golden file at `path`, creating its directory if
+/// needed.
+pub fn update_golden<P: AsRef<Path>>(path: P, actual: &str) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, actual)
+}
+
+/// Compare `actual` with the contents of the golden file at `path`.
+///
+/// If the environment variable `ACTIONS_UPDATE_GOLDEN` is set, the golden
+/// file is updated with `actual` instead.
+pub fn check_golden<P: AsRef<Path>>(path: P, actual: &str) -> Result<(), GoldenError> {
+    let update = match env::var_os(UPDATE_GOLDEN_VAR) {
+        Some(value) => value != "0",
+        None => false,
+    };
+    if update {
+        return Ok(update_golden(path, actual)?);
+    }
+    compare_golden(path, actual)
+}
+
+/// Compare `actual` with the contents of the golden file at `path`, like
+/// `check_golden`, but never update it.
+///
+/// Line endings are compared as `\n`, so a golden file that was checked out
+/// with `\r\n` line endings still matches.
+pub fn compare_golden<P: AsRef<Path>>(path: P, actual: &str) -> Result<(), GoldenError> {
+    let path = path.as_ref();
+    let expected = match fs::read_to_string(path) {
+        Ok(expected) => expected,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(GoldenError::Missing(path.to_path_buf()))
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let expected = expected.replace("\r\n", "\n");
+    let actual = actual.replace("\r\n", "\n");
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(GoldenError::Mismatch {
+            path: path.to_path_buf(),
+            diff: diff(&expected, &actual),
+        })
+    }
+}
+
+/// Compress `chain` and panic if the rendered result differs from the
+/// golden file at `path`. See `render_compression` and `check_golden`.
+pub fn assert_golden_compression<A, P>(path: P, chain: &Chain<A>)
+where
+    A: Merge + Clone + fmt::Debug,
+    P: AsRef<Path>,
+{
+    if let Err(e) = check_golden(path, &render_compression(chain)) {
+        panic!("{}", e);
+    }
+}
@@ -0,0 +1,322 @@
+use crate::chain::Chain;
+use crate::equivalence::Outcome;
+use crate::merge::{Merge, MergeResult};
+use crate::state::{InverseResult, State};
+use crate::testing::gen::{shrink, shrink_chain, Shrink};
+
+use std::fmt;
+
+/// A law that implementations of `State` and `Merge` should follow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Law {
+    /// Applying an action and then its inverse gives back the original state.
+    Inverse,
+    /// Applying `action.merge(previous)` has the same effect as applying
+    /// `previous` and then `action`.
+    Merge,
+    /// Applying a compressed chain has the same effect as applying the
+    /// original chain.
+    CompressPreserves,
+    /// Merging three actions has the same effect however the merges are
+    /// bracketed.
+    Associativity,
+    /// Two actions that cancel each other out have no effect on any state.
+    CancelsOut,
+    /// An action that overwrites the previous action has the same effect
+    /// without it.
+    OverwritesPrevious,
+    /// An action that overwrites the whole state has the same effect
+    /// without any of the actions before it.
+    OverwritesState,
+}
+
+/// A case that breaks a `Law`.
+///
+/// Applying `left` and `right` to `state` should have given the same state,
+/// but applying `left` gave `expected` and applying `right` gave `actual`.
+pub struct Violation<S: State>
+where
+    S::Action: Clone,
+{
+    /// The law that was broken.
+    pub law: Law,
+    /// The number of cases that passed before this case.
+    pub case: usize,
+    /// The generated state.
+    pub state: S,
+    /// The actions whose effect should have been reproduced.
+    pub left: Chain<S::Action>,
+    /// The actions that should have had the same effect as `left`.
+    pub right: Chain<S::Action>,
+    /// The result of applying `left` to `state`.
+    pub expected: S,
+    /// The result of applying `right` to `state`.
+    pub actual: Outcome<S>,
+}
+
+impl<S> fmt::Debug for Violation<S>
+where
+    S: State + fmt::Debug,
+    S::Action: Clone + fmt::Debug,
+    S::Error: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Violation")
+            .field("law", &self.law)
+            .field("case", &self.case)
+            .field("state", &self.state)
+            .field("left", &self.left)
+            .field("right", &self.right)
+            .field("expected", &self.expected)
+            .field("actual", &self.actual)
+            .finish()
+    }
+}
+
+impl<S> fmt::Display for Violation<S>
+where
+    S: State + fmt::Debug,
+    S::Action: Clone + fmt::Debug,
+    S::Error: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:?} law broken by case {}:", self.law, self.case)?;
+        writeln!(f, "  state:    {:?}", self.state)?;
+        writeln!(f, "  left:     {:?}", self.left.actions())?;
+        writeln!(f, "  right:    {:?}", self.right.actions())?;
+        writeln!(f, "  expected: {:?}", self.expected)?;
+        write!(f, "  actual:   {:?}", self.actual)
+    }
+}
+
+/// Check that applying an action and then its inverse gives back the
+/// original state, for `cases` generated states and actions.
+///
+/// `gen_action` should return an action that can be applied to the given
+/// state. Cases where applying the action fails, or where the inverse
+/// requires a full copy of the state, are skipped. A state and action that
+/// break the law are shrunk for as long as they still break it.
+pub fn check_inverse_law<S, GS, GA>(
+    cases: usize,
+    mut gen_state: GS,
+    mut gen_action: GA,
+) -> Result<(), Violation<S>>
+where
+    S: State + Shrink + Clone + PartialEq,
+    S::Action: Shrink + Clone,
+    GS: FnMut() -> S,
+    GA: FnMut(&S) -> S::Action,
+{
+    for case in 0..cases {
+        let state = gen_state();
+        let action = gen_action(&state);
+        if inverse_violation(&state, &action).is_none() {
+            continue;
+        }
+
+        let (state, action) = shrink((state, action), |(state, action)| {
+            inverse_violation(state, action).is_some()
+        });
+        let (right, actual) = inverse_violation(&state, &action).unwrap();
+        return Err(Violation {
+            law: Law::Inverse,
+            case,
+            expected: state.clone(),
+            state,
+            left: Chain::new(),
+            right,
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+/// Return the action followed by its inverse and the result of applying
+/// them if they do not give back `state`.
+///
+/// An action that can not be applied, or that has no inverse action, does
+/// not break the law.
+fn inverse_violation<S>(state: &S, action: &S::Action) -> Option<(Chain<S::Action>, Outcome<S>)>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone,
+{
+    let inverse = match state.inverse(action) {
+        InverseResult::Action(inverse) => inverse,
+        InverseResult::FullCopyRequired => return None,
+    };
+    if state.clone().apply(action).is_err() {
+        return None;
+    }
+
+    let right: Chain<S::Action> = vec![action.clone(), inverse].into();
+    let actual = Outcome::of(&right, state);
+    if actual.matches(&Outcome::Applied(state.clone())) {
+        None
+    } else {
+        Some((right, actual))
+    }
+}
+
+/// Check that merging two actions does not change their effect, for `cases`
+/// generated states and pairs of actions.
+///
+/// The second action is generated for the state after applying the first.
+/// Cases where applying either action fails, or where the actions are
+/// unmergable, are skipped. A state and actions that break the law are
+/// shrunk for as long as they still break it.
+pub fn check_merge_law<S, GS, GA>(
+    cases: usize,
+    mut gen_state: GS,
+    mut gen_action: GA,
+) -> Result<(), Violation<S>>
+where
+    S: State + Shrink + Clone + PartialEq,
+    S::Action: Shrink + Clone + Merge,
+    GS: FnMut() -> S,
+    GA: FnMut(&S) -> S::Action,
+{
+    for case in 0..cases {
+        let state = gen_state();
+        let previous = gen_action(&state);
+        let mut scratch = state.clone();
+        if scratch.apply(&previous).is_err() {
+            continue;
+        }
+        let action = gen_action(&scratch);
+        if merge_violation(&state, &previous, &action).is_none() {
+            continue;
+        }
+
+        let (state, previous, action) = shrink((state, previous, action), |(s, p, a)| {
+            merge_violation(s, p, a).is_some()
+        });
+        let (expected, right, actual) = merge_violation(&state, &previous, &action).unwrap();
+        return Err(Violation {
+            law: Law::Merge,
+            case,
+            state,
+            left: vec![previous, action].into(),
+            right,
+            expected,
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+/// Return the expected state, the merged actions and the result of applying
+/// them if merging `action` into `previous` changes their effect on `state`.
+///
+/// Actions that can not be applied, or that are unmergable, do not break
+/// the law.
+fn merge_violation<S>(
+    state: &S,
+    previous: &S::Action,
+    action: &S::Action,
+) -> Option<(S, Chain<S::Action>, Outcome<S>)>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone + Merge,
+{
+    let mut expected = state.clone();
+    if expected.apply(previous).is_err() || expected.apply(action).is_err() {
+        return None;
+    }
+
+    let right: Chain<S::Action> = match action.merge(previous) {
+        MergeResult::Unmergable => return None,
+        MergeResult::CancelsOut => Chain::new(),
+        MergeResult::OverwritesPrevious | MergeResult::OverwritesState => {
+            vec![action.clone()].into()
+        }
+        MergeResult::Merged(merged) => vec![merged].into(),
+        MergeResult::Replace(actions) => actions.into(),
+    };
+
+    let actual = Outcome::of(&right, state);
+    if actual.matches(&Outcome::Applied(expected.clone())) {
+        None
+    } else {
+        Some((expected, right, actual))
+    }
+}
+
+/// Check that `Chain::compress` does not change the effect of a chain, for
+/// `cases` generated states and chains of up to `max_len` actions.
+///
+/// Every action is generated for the state after applying the actions
+/// before it. A chain that breaks the law is shrunk by removing actions
+/// for as long as the smaller chain still breaks the law.
+pub fn check_compress_preserves<S, GS, GA>(
+    cases: usize,
+    max_len: usize,
+    mut gen_state: GS,
+    mut gen_action: GA,
+) -> Result<(), Violation<S>>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone + Merge,
+    GS: FnMut() -> S,
+    GA: FnMut(&S) -> S::Action,
+{
+    for case in 0..cases {
+        let state = gen_state();
+        let mut scratch = state.clone();
+        let mut chain = Chain::with_capacity(max_len);
+        for _ in 0..max_len {
+            let action = gen_action(&scratch);
+            if scratch.apply(&action).is_err() {
+                break;
+            }
+            chain.push(action);
+        }
+
+        if compress_violation(&state, &chain).is_none() {
+            continue;
+        }
+
+        let left = shrink_chain(chain, |chain| compress_violation(&state, chain).is_some());
+        let (expected, right, actual) = compress_violation(&state, &left).unwrap();
+        return Err(Violation {
+            law: Law::CompressPreserves,
+            case,
+            state,
+            left,
+            right,
+            expected,
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+/// Return the expected state, the compressed chain and the result of
+/// applying it if compressing `chain` changes its effect on `state`.
+///
+/// A chain that can not be applied to `state` does not break the law.
+fn compress_violation<S>(
+    state: &S,
+    chain: &Chain<S::Action>,
+) -> Option<(S, Chain<S::Action>, Outcome<S>)>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone + Merge,
+{
+    let expected = match Outcome::of(chain, state) {
+        Outcome::Applied(expected) => expected,
+        Outcome::Failed { .. } => return None,
+    };
+
+    let mut compressed = chain.clone();
+    compressed.compress();
+    let actual = Outcome::of(&compressed, state);
+    if actual.matches(&Outcome::Applied(expected.clone())) {
+        None
+    } else {
+        Some((expected, compressed, actual))
+    }
+}
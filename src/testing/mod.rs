@@ -0,0 +1,61 @@
+//! Helpers to test implementations of `State` and `Merge`.
+//!
+//! Only available with the `testing` feature.
+//!
+//! The checkers generate random cases with the generators they are given,
+//! and return the first case that breaks a law, made as small as possible.
+//! `GenState` and `GenAction` generate the cases from a seed, so that a
+//! failing case can be reproduced. `check_timeline` compares a `Timeline`
+//! with a naive model that keeps a copy of every state.
+//!
+//! A `Scenario`, usually built with the `scenario!` macro, lists operations
+//! on a `Timeline` and the state each should lead to, for hand-written tests.
+//!
+//! `assert_golden_compression` compares the compression of a chain with a
+//! golden file, which is updated when `ACTIONS_UPDATE_GOLDEN=1` is set.
+//!
+//! `fuzz_compress` and `fuzz_timeline` take arbitrary bytes and test this
+//! crate with the built-in `Register` state, for example with `cargo fuzz`:
+//!
+//! ```ignore
+//! #![no_main]
+//! use libfuzzer_sys::fuzz_target;
+//!
+//! fuzz_target!(|data: &[u8]| actions::testing::fuzz_compress(data));
+//! ```
+
+/// The version of `rand` used by `GenState` and `GenAction`.
+pub use rand;
+
+mod algebra;
+pub use self::algebra::{check_associativity, check_cancels_out, check_overwrites};
+
+mod fuzz;
+pub use self::fuzz::{fuzz_compress, fuzz_timeline};
+
+mod gen;
+pub use self::gen::{
+    check_laws, gen_chain, seeded_rng, shrink, shrink_chain, GenAction, GenState, Shrink,
+};
+
+mod golden;
+pub use self::golden::{
+    assert_golden_compression, check_golden, compare_golden, render_compression, update_golden,
+    GoldenError, UPDATE_GOLDEN_VAR,
+};
+
+mod laws;
+pub use self::laws::{
+    check_compress_preserves, check_inverse_law, check_merge_law, Law, Violation,
+};
+
+mod model;
+pub use self::model::{
+    check_timeline, run_timeline_model, Observation, SnapshotModel, TimelineMismatch,
+};
+
+mod register;
+pub use self::register::{Register, RegisterAction, RegisterError};
+
+mod scenario;
+pub use self::scenario::{Expect, Scenario, ScenarioFailure, Step};
@@ -0,0 +1,256 @@
+use crate::state::State;
+use crate::testing::gen::{gen_chain, seeded_rng, shrink_chain, GenAction, GenState};
+use crate::timeline::{Timeline, TimelineOp};
+
+use rand::Rng;
+
+use std::fmt;
+
+/// The maximum length of the chains applied by `check_timeline`.
+const MAX_CHAIN_LEN: usize = 4;
+
+/// A naive model of a `Timeline` that keeps a full copy of the state after
+/// every action, to compare a `Timeline` with.
+#[derive(Clone, Debug)]
+pub struct SnapshotModel<S> {
+    snapshots: Vec<S>,
+    position: usize,
+}
+
+impl<S: State + Clone> SnapshotModel<S>
+where
+    S::Action: Clone,
+{
+    /// Create a new model that starts at `state`.
+    pub fn new(state: S) -> Self {
+        Self {
+            snapshots: vec![state],
+            position: 0,
+        }
+    }
+
+    /// Perform `op` the way a `Timeline` should.
+    ///
+    /// # Return
+    /// `true` if the operation succeeded. Like `Timeline::apply_chain`, a
+    /// chain stops at the first action that fails and keeps the actions
+    /// before it.
+    pub fn perform(&mut self, op: &TimelineOp<S::Action>) -> bool {
+        match op {
+            TimelineOp::Apply(action) => self.apply(action),
+            TimelineOp::Undo => {
+                if self.position == 0 {
+                    return false;
+                }
+                self.position -= 1;
+                true
+            }
+            TimelineOp::Redo => {
+                if self.position + 1 == self.snapshots.len() {
+                    return false;
+                }
+                self.position += 1;
+                true
+            }
+            TimelineOp::ApplyChain(chain) => chain.iter().all(|action| self.apply(action)),
+        }
+    }
+
+    fn apply(&mut self, action: &S::Action) -> bool {
+        let mut next = self.current_state().clone();
+        if next.apply(action).is_err() {
+            return false;
+        }
+
+        self.snapshots.truncate(self.position + 1);
+        self.snapshots.push(next);
+        self.position += 1;
+        true
+    }
+
+    /// Get a reference to the current state.
+    pub fn current_state(&self) -> &S {
+        &self.snapshots[self.position]
+    }
+
+    /// Return the number of undo's that can be performed.
+    pub fn undos_remaining(&self) -> usize {
+        self.position
+    }
+
+    /// Return the number of redo's that can be performed.
+    pub fn redos_remaining(&self) -> usize {
+        self.snapshots.len() - self.position - 1
+    }
+}
+
+/// What can be observed of a `Timeline` or `SnapshotModel` after an operation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation<S> {
+    /// Whether the operation succeeded.
+    pub succeeded: bool,
+    /// The current state.
+    pub state: S,
+    /// The number of undo's that can be performed.
+    pub undos_remaining: usize,
+    /// The number of redo's that can be performed.
+    pub redos_remaining: usize,
+}
+
+/// A sequence of operations after which a `Timeline` and its `SnapshotModel`
+/// differ.
+pub struct TimelineMismatch<S: State>
+where
+    S::Action: Clone,
+{
+    /// The state both started at.
+    pub initial: S,
+    /// The operations that were performed. The last operation made them differ.
+    pub ops: Vec<TimelineOp<S::Action>>,
+    /// What the model observed after the last operation.
+    pub expected: Observation<S>,
+    /// What the timeline observed after the last operation.
+    pub actual: Observation<S>,
+}
+
+impl<S> fmt::Debug for TimelineMismatch<S>
+where
+    S: State + fmt::Debug,
+    S::Action: Clone + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimelineMismatch")
+            .field("initial", &self.initial)
+            .field("ops", &self.ops)
+            .field("expected", &self.expected)
+            .field("actual", &self.actual)
+            .finish()
+    }
+}
+
+impl<S> fmt::Display for TimelineMismatch<S>
+where
+    S: State + fmt::Debug,
+    S::Action: Clone + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "the timeline differs from the model, starting at {:?}:",
+            self.initial
+        )?;
+        for op in &self.ops {
+            writeln!(f, "  {:?}", op)?;
+        }
+        writeln!(f, "expected: {:?}", self.expected)?;
+        write!(f, "actual:   {:?}", self.actual)
+    }
+}
+
+/// Perform `ops` on both a `Timeline` and a `SnapshotModel` that start at
+/// `state`, and compare them after every operation.
+///
+/// # Return
+/// The number of operations performed, or the operations up to the first
+/// operation after which they differ.
+pub fn run_timeline_model<S, I>(state: S, ops: I) -> Result<usize, TimelineMismatch<S>>
+where
+    S: State + Clone + PartialEq,
+    S::Action: Clone,
+    I: IntoIterator<Item = TimelineOp<S::Action>>,
+{
+    let mut timeline = Timeline::new(state.clone());
+    let mut model = SnapshotModel::new(state.clone());
+    let mut performed = Vec::new();
+
+    for op in ops {
+        let actual = perform_on_timeline(&mut timeline, &op);
+        let expected = Observation {
+            succeeded: model.perform(&op),
+            state: model.current_state().clone(),
+            undos_remaining: model.undos_remaining(),
+            redos_remaining: model.redos_remaining(),
+        };
+        performed.push(op);
+
+        if actual != expected {
+            return Err(TimelineMismatch {
+                initial: state,
+                ops: performed,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    Ok(performed.len())
+}
+
+/// Perform `steps` random operations on a `Timeline` and a `SnapshotModel`,
+/// for `cases` generated states, and compare them after every operation.
+///
+/// Running it again with the same seed performs the exact same operations.
+/// The operations of a mismatch are shrunk by removing operations for as
+/// long as the timeline and the model still differ.
+pub fn check_timeline<S>(seed: u64, cases: usize, steps: usize) -> Result<(), TimelineMismatch<S>>
+where
+    S: GenState + Clone + PartialEq,
+    S::Action: GenAction<S> + Clone,
+{
+    let mut rng = seeded_rng(seed);
+
+    for _ in 0..cases {
+        let state = S::gen_state(&mut rng);
+
+        // The actions are generated for the state of the model, so that
+        // most of them can be applied.
+        let mut model = SnapshotModel::new(state.clone());
+        let mut ops = Vec::with_capacity(steps);
+        for _ in 0..steps {
+            let op = gen_op(model.current_state(), &mut rng);
+            model.perform(&op);
+            ops.push(op);
+        }
+
+        if run_timeline_model(state.clone(), ops.clone()).is_ok() {
+            continue;
+        }
+
+        let ops = shrink_chain(ops.into(), |ops| {
+            run_timeline_model(state.clone(), ops.iter().cloned()).is_err()
+        });
+        return run_timeline_model(state, ops).map(|_| ());
+    }
+
+    Ok(())
+}
+
+fn gen_op<S, R>(state: &S, rng: &mut R) -> TimelineOp<S::Action>
+where
+    S: State + Clone,
+    S::Action: GenAction<S> + Clone,
+    R: Rng + ?Sized,
+{
+    match rng.gen_range(0, 10) {
+        0..=3 => TimelineOp::Apply(S::Action::gen_action(state, rng)),
+        4 | 5 => TimelineOp::Undo,
+        6 | 7 => TimelineOp::Redo,
+        _ => {
+            let len = rng.gen_range(1, MAX_CHAIN_LEN + 1);
+            TimelineOp::ApplyChain(gen_chain(state, len, rng))
+        }
+    }
+}
+
+fn perform_on_timeline<S>(timeline: &mut Timeline<S>, op: &TimelineOp<S::Action>) -> Observation<S>
+where
+    S: State + Clone,
+    S::Action: Clone,
+{
+    Observation {
+        succeeded: timeline.perform(op).is_ok(),
+        state: timeline.current_state().clone(),
+        undos_remaining: timeline.undos_remaining(),
+        redos_remaining: timeline.redos_remaining(),
+    }
+}
@@ -0,0 +1,188 @@
+use crate::merge::{Commute, Merge, MergeResult};
+use crate::state::{InverseResult, State};
+use crate::testing::gen::{GenAction, GenState, Shrink};
+
+use rand::Rng;
+
+use std::fmt;
+
+/// A reference `State` to test this crate and its fuzz targets with.
+///
+/// It holds a single number. Arithmetic wraps around, so only `Div` can fail.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Register(pub i64);
+
+/// The actions of a `Register`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RegisterAction {
+    /// Add a number.
+    Add(i64),
+    /// Multiply by a number.
+    Mul(i64),
+    /// Set the register to a number.
+    Set(i64),
+    /// Negate the number.
+    Neg,
+    /// Exclusive or with a number.
+    Xor(i64),
+    /// Divide by a number, rounding towards zero.
+    Div(i64),
+}
+
+/// The error of a `Register` action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterError {
+    /// `Div(0)` was applied.
+    DivideByZero,
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterError::DivideByZero => write!(f, "Tried to divide the register by zero."),
+        }
+    }
+}
+
+impl State for Register {
+    type Action = RegisterAction;
+    type Error = RegisterError;
+
+    fn apply(&mut self, action: &RegisterAction) -> Result<(), RegisterError> {
+        self.0 = match *action {
+            RegisterAction::Add(n) => self.0.wrapping_add(n),
+            RegisterAction::Mul(n) => self.0.wrapping_mul(n),
+            RegisterAction::Set(n) => n,
+            RegisterAction::Neg => self.0.wrapping_neg(),
+            RegisterAction::Xor(n) => self.0 ^ n,
+            RegisterAction::Div(0) => return Err(RegisterError::DivideByZero),
+            RegisterAction::Div(n) => self.0.wrapping_div(n),
+        };
+        Ok(())
+    }
+
+    fn inverse(&self, action: &RegisterAction) -> InverseResult<RegisterAction> {
+        match *action {
+            RegisterAction::Add(n) => InverseResult::Action(RegisterAction::Add(n.wrapping_neg())),
+            RegisterAction::Neg => InverseResult::Action(RegisterAction::Neg),
+            RegisterAction::Xor(n) => InverseResult::Action(RegisterAction::Xor(n)),
+            RegisterAction::Mul(_) | RegisterAction::Set(_) | RegisterAction::Div(_) => {
+                InverseResult::FullCopyRequired
+            }
+        }
+    }
+}
+
+impl Merge for RegisterAction {
+    fn merge(&self, previous: &Self) -> MergeResult<Self> {
+        use self::RegisterAction::*;
+
+        match (*previous, *self) {
+            (_, Set(_)) => MergeResult::OverwritesState,
+            (Add(a), Add(b)) => match a.wrapping_add(b) {
+                0 => MergeResult::CancelsOut,
+                n => MergeResult::Merged(Add(n)),
+            },
+            (Mul(a), Mul(b)) => MergeResult::Merged(Mul(a.wrapping_mul(b))),
+            (Neg, Neg) => MergeResult::CancelsOut,
+            (Xor(a), Xor(b)) => match a ^ b {
+                0 => MergeResult::CancelsOut,
+                n => MergeResult::Merged(Xor(n)),
+            },
+            // Dividing by zero must keep failing.
+            (Set(_), Div(0)) => MergeResult::Unmergable,
+            (Set(v), action) => {
+                let mut register = Register(v);
+                match register.apply(&action) {
+                    Ok(()) => MergeResult::Merged(Set(register.0)),
+                    Err(_) => MergeResult::Unmergable,
+                }
+            }
+            _ => MergeResult::Unmergable,
+        }
+    }
+}
+
+impl Commute for RegisterAction {
+    fn commutes_with(&self, other: &Self) -> bool {
+        use self::RegisterAction::*;
+
+        matches!(
+            (*self, *other),
+            (Add(_), Add(_))
+                | (Mul(_), Mul(_))
+                | (Xor(_), Xor(_))
+                | (Neg, Neg)
+                | (Neg, Mul(_))
+                | (Mul(_), Neg)
+        )
+    }
+}
+
+impl GenState for Register {
+    fn gen_state<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        // Small numbers make it more likely that actions cancel out.
+        if rng.gen() {
+            Register(rng.gen_range(-16, 16))
+        } else {
+            Register(rng.gen())
+        }
+    }
+}
+
+impl GenAction<Register> for RegisterAction {
+    fn gen_action<R: Rng + ?Sized>(_: &Register, rng: &mut R) -> Self {
+        let n = rng.gen_range(-4, 5);
+        match rng.gen_range(0, 6) {
+            0 => RegisterAction::Add(n),
+            1 => RegisterAction::Mul(n),
+            2 => RegisterAction::Set(n),
+            3 => RegisterAction::Neg,
+            4 => RegisterAction::Xor(n),
+            _ => RegisterAction::Div(if n == 0 { 1 } else { n }),
+        }
+    }
+}
+
+/// Return the numbers between 0 and `n` to shrink `n` to, 0 first.
+fn towards_zero(n: i64) -> Vec<i64> {
+    let mut smaller = vec![0, n / 2];
+    smaller.dedup();
+    smaller.retain(|&m| m != n);
+    smaller
+}
+
+impl Shrink for Register {
+    fn shrink(&self) -> Vec<Self> {
+        towards_zero(self.0).into_iter().map(Register).collect()
+    }
+}
+
+impl Shrink for RegisterAction {
+    fn shrink(&self) -> Vec<Self> {
+        match *self {
+            RegisterAction::Add(n) => towards_zero(n)
+                .into_iter()
+                .map(RegisterAction::Add)
+                .collect(),
+            RegisterAction::Mul(n) => towards_zero(n)
+                .into_iter()
+                .map(RegisterAction::Mul)
+                .collect(),
+            RegisterAction::Set(n) => towards_zero(n)
+                .into_iter()
+                .map(RegisterAction::Set)
+                .collect(),
+            RegisterAction::Neg => Vec::new(),
+            RegisterAction::Xor(n) => towards_zero(n)
+                .into_iter()
+                .map(RegisterAction::Xor)
+                .collect(),
+            // Dividing by 0 fails, so shrink towards 1 instead.
+            RegisterAction::Div(n) => towards_zero(n - n.signum())
+                .into_iter()
+                .map(|m| RegisterAction::Div(m + n.signum()))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use crate::chain::Chain;
+use crate::recorder::OpOutcome;
+use crate::state::State;
+use crate::store::HistoryStore;
+use crate::timeline::{Timeline, TimelineOp};
+
+use std::fmt;
+
+/// What a step of a `Scenario` expects.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expect<T> {
+    /// The operation succeeds and the projection of the state is equal to
+    /// the value.
+    Value(T),
+    /// The operation fails with this outcome.
+    Error(OpOutcome),
+}
+
+/// An operation of a `Scenario` and what it expects.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step<A: Clone, T> {
+    /// The operation to perform.
+    pub op: TimelineOp<A>,
+    /// What the operation should lead to.
+    pub expect: Expect<T>,
+}
+
+/// A list of operations on a `Timeline` and what every operation should
+/// lead to, for writing tests of a `Timeline`.
+///
+/// The state is compared through a projection, for example the value of a
+/// counter, so that the expected values stay short. Usually built with the
+/// `scenario!` macro:
+///
+/// ```
+/// # #[cfg(feature = "testing")]
+/// # {
+/// use actions::scenario;
+/// use actions::testing::{Register, RegisterAction};
+/// use actions::Timeline;
+///
+/// let mut timeline = Timeline::new(Register(0));
+/// scenario![
+///     apply(RegisterAction::Add(1)) => 1,
+///     undo => 0,
+///     undo => Err(NothingToUndo),
+///     redo => 1,
+/// ]
+/// .run(&mut timeline, |register| register.0);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario<A: Clone, T> {
+    steps: Vec<Step<A, T>>,
+}
+
+impl<A: Clone, T> Default for Scenario<A, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Clone, T> Scenario<A, T> {
+    /// Create a new `Scenario` without any steps.
+    pub fn new() -> Self {
+        Self { steps: Vec::new() }
+    }
+
+    /// Add a step that performs `op` and expects `expect`.
+    pub fn step(mut self, op: TimelineOp<A>, expect: Expect<T>) -> Self {
+        self.steps.push(Step { op, expect });
+        self
+    }
+
+    /// Add a step that applies `action` and expects the projection to be
+    /// `expected` after it.
+    pub fn apply(self, action: A, expected: T) -> Self {
+        self.step(TimelineOp::Apply(action), Expect::Value(expected))
+    }
+
+    /// Add a step that applies `chain` and expects the projection to be
+    /// `expected` after it.
+    pub fn apply_chain(self, chain: Chain<A>, expected: T) -> Self {
+        self.step(TimelineOp::ApplyChain(chain), Expect::Value(expected))
+    }
+
+    /// Add a step that undoes and expects the projection to be `expected`
+    /// after it.
+    pub fn undo(self, expected: T) -> Self {
+        self.step(TimelineOp::Undo, Expect::Value(expected))
+    }
+
+    /// Add a step that redoes and expects the projection to be `expected`
+    /// after it.
+    pub fn redo(self, expected: T) -> Self {
+        self.step(TimelineOp::Redo, Expect::Value(expected))
+    }
+
+    /// Add a step that performs `op` and expects it to fail with `outcome`.
+    pub fn fails(self, op: TimelineOp<A>, outcome: OpOutcome) -> Self {
+        self.step(op, Expect::Error(outcome))
+    }
+
+    /// Get the steps of the scenario.
+    pub fn steps(&self) -> &[Step<A, T>] {
+        &self.steps
+    }
+
+    /// Perform the steps on `timeline`, one after the other.
+    ///
+    /// # Return
+    /// The first step that did not lead to what it expected, together with
+    /// every step performed up to it.
+    pub fn check<S, H, P>(
+        &self,
+        timeline: &mut Timeline<S, H>,
+        projection: P,
+    ) -> Result<(), ScenarioFailure<A, T>>
+    where
+        S: State<Action = A> + Clone,
+        H: HistoryStore<S>,
+        T: Clone + PartialEq,
+        P: Fn(&S) -> T,
+    {
+        let mut observed = Vec::with_capacity(self.steps.len());
+
+        for (index, step) in self.steps.iter().enumerate() {
+            let outcome = OpOutcome::of(&timeline.perform(&step.op));
+            let value = projection(timeline.current_state());
+
+            let met = match &step.expect {
+                Expect::Value(expected) => outcome == OpOutcome::Succeeded && value == *expected,
+                Expect::Error(expected) => outcome == *expected,
+            };
+            observed.push((outcome, value));
+
+            if !met {
+                return Err(ScenarioFailure {
+                    step: index,
+                    steps: self.steps[..=index].to_vec(),
+                    observed,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Perform the steps on `timeline` and panic with a trace of the steps
+    /// if one did not lead to what it expected.
+    pub fn run<S, H, P>(&self, timeline: &mut Timeline<S, H>, projection: P)
+    where
+        S: State<Action = A> + Clone,
+        H: HistoryStore<S>,
+        A: fmt::Debug,
+        T: Clone + PartialEq + fmt::Debug,
+        P: Fn(&S) -> T,
+    {
+        if let Err(failure) = self.check(timeline, projection) {
+            panic!("The scenario failed: {}", failure);
+        }
+    }
+}
+
+/// The first step of a `Scenario` that did not lead to what it expected.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioFailure<A: Clone, T> {
+    /// The index of the step.
+    pub step: usize,
+    /// The steps that were performed, the failing step last.
+    pub steps: Vec<Step<A, T>>,
+    /// The outcome of every performed step and the projection of the state
+    /// after it.
+    pub observed: Vec<(OpOutcome, T)>,
+}
+
+impl<A: Clone + fmt::Debug, T: fmt::Debug> fmt::Display for ScenarioFailure<A, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "step {} did not lead to what it expected", self.step)?;
+        for (index, (step, (outcome, value))) in self.steps.iter().zip(&self.observed).enumerate() {
+            write!(
+                f,
+                "  {}: {:?} -> {:?}, {:?}",
+                index, step.op, outcome, value
+            )?;
+            if index == self.step {
+                write!(f, "  <- expected {:?}", step.expect)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Build a `Scenario` from a list of operations and what they expect.
+///
+/// Every step is an operation: `apply(action)`, `apply_chain(chain)`,
+/// `undo` or `redo`, followed by `=>` and the expected projection of the
+/// state, or `Err(outcome)` with the expected `OpOutcome` of a failing
+/// operation.
+///
+/// ```
+/// # #[cfg(feature = "testing")]
+/// # {
+/// use actions::scenario;
+/// use actions::testing::{Register, RegisterAction};
+/// use actions::Timeline;
+///
+/// let mut timeline = Timeline::new(Register(2));
+/// scenario![
+///     apply(RegisterAction::Mul(3)) => 6,
+///     apply(RegisterAction::Div(0)) => Err(ApplyFailed),
+///     undo => 2,
+///     undo => Err(NothingToUndo),
+/// ]
+/// .run(&mut timeline, |register| register.0);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! scenario {
+    ($($steps:tt)*) => {
+        $crate::__scenario_steps!($crate::testing::Scenario::new(); $($steps)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scenario_steps {
+    ($scenario:expr;) => {
+        $scenario
+    };
+    ($scenario:expr; $op:ident $(($($arg:expr),*))? => Err($outcome:ident) $(, $($rest:tt)*)?) => {
+        $crate::__scenario_steps!(
+            $scenario.fails(
+                $crate::__scenario_op!($op $(($($arg),*))?),
+                $crate::OpOutcome::$outcome,
+            );
+            $($($rest)*)?
+        )
+    };
+    ($scenario:expr; $op:ident $(($($arg:expr),*))? => $expected:expr $(, $($rest:tt)*)?) => {
+        $crate::__scenario_steps!(
+            $scenario.step(
+                $crate::__scenario_op!($op $(($($arg),*))?),
+                $crate::testing::Expect::Value($expected),
+            );
+            $($($rest)*)?
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scenario_op {
+    (apply($action:expr)) => {
+        $crate::TimelineOp::Apply($action)
+    };
+    (apply_chain($chain:expr)) => {
+        $crate::TimelineOp::ApplyChain($chain)
+    };
+    (undo) => {
+        $crate::TimelineOp::Undo
+    };
+    (redo) => {
+        $crate::TimelineOp::Redo
+    };
+}
@@ -1,14 +1,17 @@
+use crate::audit::{equal, equal_hashes, same_result};
+use crate::coverage::{inline_size, variant_name, InverseReport, InverseTracker, KindFn, SizeFn};
 use crate::state::{InverseResult, State};
 
 use std::fmt;
-use std::ops::Deref;
+use std::hash::Hash;
+use std::io::{self, Read, Write};
 
 use crate::chain::Chain;
-
-enum Breadcrumb<S: State> {
-    FullCopy(Box<S>),
-    Action(S::Action),
-}
+use crate::codec::{invalid_data, Codec};
+use crate::error::LoadError;
+use crate::format::FormatHeader;
+use crate::integrity::{Linking, RecordReader, RecordWriter};
+use crate::store::{Breadcrumb, Entry, HistoryStore, MemoryStore};
 
 /// The `Timeline` wraps around a `State` and makes it accessible by using actions only.
 ///
@@ -17,29 +20,113 @@ enum Breadcrumb<S: State> {
 /// It is **highly recommended** to implement `fn inverse(&self, action)` on the state.
 /// If the inverse function is not implemented, the Timeline will create
 /// a full clone of the state *on the heap* before every action.
-pub struct Timeline<S: State + Clone> {
-    timeline: Vec<(S::Action, Breadcrumb<S>)>,
+///
+/// The history is kept in a `HistoryStore`. By default that is a
+/// `MemoryStore`; use `Timeline::with_store` to keep it elsewhere.
+pub struct Timeline<S: State + Clone, H: HistoryStore<S> = MemoryStore<S>> {
+    history: H,
     available_undos: usize,
     current_state: S,
+    /// Compares the results of applying an action twice, see `enable_audit`.
+    audit: Option<fn(&S, &S) -> bool>,
+    /// Counts the inverses of applied actions, see `track_inverses`.
+    inverses: Option<InverseTracker<S>>,
 }
 
+/// An error returned by the methods of a `Timeline`.
 #[derive(Debug)]
 pub enum TimelineError<E> {
+    /// `undo` was called, but there are no actions left to undo.
     NothingToUndo,
+    /// `redo` was called, but there are no actions left to redo.
     NothingToRedo,
+    /// Applying an action, or the breadcrumb of an undo, failed.
     ApplyError(E),
+    /// Reading or writing the `HistoryStore` failed.
+    Storage(io::Error),
+    /// `history_between` was called with positions that are not in the
+    /// applied part of the history.
+    InvalidRange {
+        /// The first position.
+        from: usize,
+        /// The last position.
+        to: usize,
+    },
+    /// Applying an action to a copy of the state had another result, see
+    /// `Timeline::enable_audit`.
+    Nondeterministic,
 }
 
-impl<'a, E: fmt::Display> fmt::Display for TimelineError<E> {
+/// An operation on a `Timeline`, see `Timeline::perform`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimelineOp<A: Clone> {
+    /// `Timeline::apply`
+    Apply(A),
+    /// `Timeline::undo`
+    Undo,
+    /// `Timeline::redo`
+    Redo,
+    /// `Timeline::apply_chain`
+    ApplyChain(Chain<A>),
+}
+
+impl<A: Clone + Codec> Codec for TimelineOp<A> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            TimelineOp::Apply(action) => {
+                0u8.encode(writer)?;
+                action.encode(writer)
+            }
+            TimelineOp::Undo => 1u8.encode(writer),
+            TimelineOp::Redo => 2u8.encode(writer),
+            TimelineOp::ApplyChain(chain) => {
+                3u8.encode(writer)?;
+                chain.len().encode(writer)?;
+                for action in chain.actions() {
+                    action.encode(writer)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::decode(reader)? {
+            0 => Ok(TimelineOp::Apply(A::decode(reader)?)),
+            1 => Ok(TimelineOp::Undo),
+            2 => Ok(TimelineOp::Redo),
+            3 => Ok(TimelineOp::ApplyChain(Vec::<A>::decode(reader)?.into())),
+            _ => Err(invalid_data("invalid timeline operation")),
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for TimelineError<E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             TimelineError::NothingToUndo => write!(f, "No actions left to undo."),
             TimelineError::NothingToRedo => write!(f, "No actions left to redo."),
             TimelineError::ApplyError(e) => write!(f, "Applying action failed: {}", e),
+            TimelineError::Storage(e) => write!(f, "Accessing the history failed: {}", e),
+            TimelineError::InvalidRange { from, to } => write!(
+                f,
+                "There are no applied actions from position {} to position {}.",
+                from, to
+            ),
+            TimelineError::Nondeterministic => write!(
+                f,
+                "Applying the action to a copy of the state had another result."
+            ),
         }
     }
 }
 
+impl<E> From<io::Error> for TimelineError<E> {
+    fn from(source: io::Error) -> TimelineError<E> {
+        TimelineError::Storage(source)
+    }
+}
+
 impl<S: State + Clone> Timeline<S>
 where
     S::Action: Clone,
@@ -50,25 +137,157 @@ where
     ///
     /// - state: A datastructure containing the state.
     pub fn new(state: S) -> Self {
+        Self::with_store(state, MemoryStore::new())
+    }
+
+    /// Load a `Timeline` that was written by `save_to`.
+    ///
+    /// The history is kept in memory. Use `load_with_store` to keep it elsewhere.
+    pub fn load_from<R: Read>(reader: R) -> Result<Self, LoadError>
+    where
+        S: Codec,
+        S::Action: Codec,
+    {
+        Self::load_with_store(reader, MemoryStore::new())
+    }
+}
+
+impl<S: State + Clone, H: HistoryStore<S>> Timeline<S, H>
+where
+    S::Action: Clone,
+{
+    /// Create a new `Timeline` which wraps around an *item* and keeps its
+    /// history in `store`.
+    ///
+    /// Entries that are already in the store are treated as undoable history
+    /// that led to `state`.
+    ///
+    /// # Arguments
+    ///
+    /// - state: A datastructure containing the state.
+    /// - store: Where the history is kept.
+    pub fn with_store(state: S, store: H) -> Self {
         Self {
-            timeline: Vec::new(),
+            available_undos: store.len(),
+            history: store,
             current_state: state,
-            available_undos: 0,
+            audit: None,
+            inverses: None,
+        }
+    }
+
+    /// Write the current state and the whole history to `writer`.
+    ///
+    /// The redo branch, the position in the history and every breadcrumb are
+    /// kept. The data starts with a `FormatHeader`, followed by hash-chained
+    /// records so that loading refuses damaged or edited data.
+    pub fn save_to<W: Write>(&self, mut writer: W) -> io::Result<()>
+    where
+        S: Codec,
+        S::Action: Codec,
+    {
+        let header = FormatHeader {
+            magic: FormatHeader::TIMELINE_MAGIC,
+            version: FormatHeader::TIMELINE_VERSION,
+        };
+        header.write(&mut writer)?;
+
+        let mut records = RecordWriter::new(writer, Linking::Chained);
+        let mut payload = Vec::new();
+
+        self.history.len().encode(&mut payload)?;
+        self.available_undos.encode(&mut payload)?;
+        records.write_record(&payload)?;
+
+        payload.clear();
+        self.current_state.encode(&mut payload)?;
+        records.write_record(&payload)?;
+
+        for index in 0..self.history.len() {
+            payload.clear();
+            self.history.get(index)?.encode(&mut payload)?;
+            records.write_record(&payload)?;
+        }
+
+        records.flush()
+    }
+
+    /// Load a `Timeline` that was written by `save_to` and keep its history
+    /// in `store`.
+    ///
+    /// Any entries already in `store` are removed.
+    ///
+    /// # Return
+    /// The loaded `Timeline`, `LoadError::Corrupt` if the data was damaged or
+    /// edited, or `LoadError::UnsupportedVersion` if it was written by a newer
+    /// version of this crate.
+    pub fn load_with_store<R: Read>(mut reader: R, mut store: H) -> Result<Self, LoadError>
+    where
+        S: Codec,
+        S::Action: Codec,
+    {
+        // Older versions of the format are migrated here once they exist.
+        match FormatHeader::expect(&mut reader, FormatHeader::TIMELINE_MAGIC)? {
+            FormatHeader::TIMELINE_VERSION => {}
+            version => return Err(LoadError::UnsupportedVersion(version)),
+        }
+
+        let mut records = RecordReader::new(reader, Linking::Chained);
+
+        let meta = records.read_expected()?;
+        let mut meta = &meta[..];
+        let length = usize::decode(&mut meta)?;
+        let available_undos = usize::decode(&mut meta)?;
+        if available_undos > length {
+            return Err(invalid_data("more undos available than entries in the history").into());
+        }
+
+        let current_state = S::decode(&mut &records.read_expected()?[..])?;
+
+        store.truncate(0)?;
+        for _ in 0..length {
+            store.push(Entry::decode(&mut &records.read_expected()?[..])?)?;
         }
+        records.expect_end()?;
+
+        Ok(Self {
+            history: store,
+            available_undos,
+            current_state,
+            audit: None,
+            inverses: None,
+        })
+    }
+
+    /// Get a reference to the store that keeps the history.
+    pub fn history(&self) -> &H {
+        &self.history
     }
 
     /// Get a reference to the current state.
-    /// 
+    ///
     /// Can be useful to inspect state.
     pub fn current_state(&self) -> &S {
         &self.current_state
     }
 
     /// Apply an action.
+    ///
+    /// If the action was applied but storing it in the history failed,
+    /// `TimelineError::Storage` is returned and the action can not be undone.
     pub fn apply<'a>(&mut self, action: <S as State>::Action) -> Result<(), TimelineError<S::Error>>
     where
         S: 'a,
     {
+        let audited = match self.audit {
+            Some(same) if cfg!(debug_assertions) => {
+                let mut copy = self.current_state.clone();
+                let applied = copy.apply(&action).map(|()| copy);
+                Some((same, applied, self.current_state.clone()))
+            }
+            _ => None,
+        };
+
         let breadcrumb = match self.current_state.inverse(&action) {
             InverseResult::Action(inverse) => Breadcrumb::Action(inverse),
             InverseResult::FullCopyRequired => {
@@ -76,17 +295,35 @@ where
             }
         };
 
-        match self.current_state.apply(&action) {
+        let result = self.current_state.apply(&action);
+        if let Some((same, copy, original)) = audited {
+            let applied = result.as_ref().map(|()| &self.current_state);
+            if !same_result(copy.as_ref().map_err(|_| ()), applied.map_err(|_| ()), same) {
+                // The action is not added to the history, so undo its effect.
+                self.current_state = original;
+                return Err(TimelineError::Nondeterministic);
+            }
+        }
+
+        match result {
             Ok(()) => {
-                assert!(self.timeline.len() >= self.available_undos);
-
-                if self.timeline.len() == self.available_undos {
-                    self.timeline.push((action, breadcrumb));
-                } else
-                /*if self.timeline.len() > self.available_undos*/
-                {
-                    self.timeline[self.available_undos] = (action, breadcrumb);
-                    self.timeline.truncate(self.available_undos + 1);
+                assert!(self.history.len() >= self.available_undos);
+
+                if self.history.len() > self.available_undos {
+                    // Applying an action discards the redo branch.
+                    self.history.truncate(self.available_undos)?;
+                }
+                let measured = self.inverses.as_ref().map(|tracker| {
+                    let full_copy = match &breadcrumb {
+                        Breadcrumb::FullCopy(state) => Some(&**state),
+                        Breadcrumb::Action(_) => None,
+                    };
+                    tracker.measure(&action, full_copy)
+                });
+                self.history.push(Entry { action, breadcrumb })?;
+                // Only actions that made it into the history are counted.
+                if let (Some(tracker), Some(measured)) = (&mut self.inverses, measured) {
+                    tracker.record(measured);
                 }
 
                 self.available_undos += 1;
@@ -111,24 +348,60 @@ where
         Ok(())
     }
 
+    /// Return the actions that lead from one position in the history to another.
+    ///
+    /// A position is the number of undos that are available at that moment,
+    /// as returned by `undos_remaining`. Remember it when the user saves to
+    /// get the changes between two saves, for example to create a `Changelog`.
+    ///
+    /// # Return
+    /// The actions, or `TimelineError::InvalidRange` if `from` is larger than
+    /// `to`, or `to` is larger than `undos_remaining`. Actions that were
+    /// undone are not part of the current state, so they can not be returned.
+    pub fn history_between(
+        &self,
+        from: usize,
+        to: usize,
+    ) -> Result<Chain<S::Action>, TimelineError<S::Error>> {
+        if from > to || to > self.available_undos {
+            return Err(TimelineError::InvalidRange { from, to });
+        }
+
+        let mut chain = Chain::with_capacity(to - from);
+        for index in from..to {
+            chain.push(self.history.get_action(index)?);
+        }
+        Ok(chain)
+    }
+
+    /// Perform `op` by calling the method of the same name.
+    pub fn perform(&mut self, op: &TimelineOp<S::Action>) -> Result<(), TimelineError<S::Error>> {
+        match op {
+            TimelineOp::Apply(action) => self.apply(action.clone()),
+            TimelineOp::Undo => self.undo(),
+            TimelineOp::Redo => self.redo(),
+            TimelineOp::ApplyChain(chain) => self.apply_chain(chain),
+        }
+    }
+
     /// Go one step back in history.
     ///
     /// # Return
-    /// A result containing either an empty `Ok` or an error if 
+    /// A result containing either an empty `Ok` or an error if
     /// there are no actions left to undo.
     pub fn undo(&mut self) -> Result<(), TimelineError<S::Error>> {
         if self.available_undos == 0 {
             return Err(TimelineError::NothingToUndo);
         };
 
-        match self.timeline[self.available_undos - 1].1 {
-            Breadcrumb::Action(ref action) => {
+        match self.history.get(self.available_undos - 1)?.breadcrumb {
+            Breadcrumb::Action(action) => {
                 self.current_state
                     .apply(&action)
                     .map_err(TimelineError::ApplyError)?;
             }
-            Breadcrumb::FullCopy(ref state) => {
-                self.current_state = state.deref().clone();
+            Breadcrumb::FullCopy(state) => {
+                self.current_state = *state;
             }
         };
 
@@ -140,17 +413,16 @@ where
     /// Go one step forward in history.
     ///
     /// # Return
-    /// A result containing either an empty `Ok` or an error if 
+    /// A result containing either an empty `Ok` or an error if
     /// there are no actions left to redo.
     pub fn redo(&mut self) -> Result<(), TimelineError<S::Error>> {
-        if self.timeline.len() == self.available_undos {
+        if self.history.len() == self.available_undos {
             return Err(TimelineError::NothingToRedo);
         };
 
-        let index = self.timeline.len() - self.available_undos;
-
+        // The first entry after the undoable ones is the next to redo.
         self.current_state
-            .apply(&self.timeline[index].0)
+            .apply(&self.history.get_action(self.available_undos)?)
             .map_err(TimelineError::ApplyError)?;
 
         self.available_undos += 1;
@@ -165,6 +437,72 @@ where
 
     /// Return the number of redo's that can be performed.
     pub fn redos_remaining(&self) -> usize {
-        self.timeline.len() - self.available_undos
+        self.history.len() - self.available_undos
+    }
+
+    /// Audit every action that is applied, in debug builds.
+    ///
+    /// Before an action is applied, it is applied to a copy of the current
+    /// state too, and `apply` returns `TimelineError::Nondeterministic`
+    /// without changing the state if the results are not equal. This catches
+    /// a `State::apply` that depends on the time, randomness or global
+    /// state. See `audit_apply`. Release builds do not audit.
+    pub fn enable_audit(&mut self)
+    where
+        S: PartialEq,
+    {
+        self.audit = Some(equal);
+    }
+
+    /// Audit every action that is applied in debug builds, like
+    /// `enable_audit`, but compare the hashes of the states.
+    pub fn enable_audit_hashed(&mut self)
+    where
+        S: Hash,
+    {
+        self.audit = Some(equal_hashes);
+    }
+
+    /// Stop auditing the actions that are applied.
+    pub fn stop_audit(&mut self) {
+        self.audit = None;
+    }
+
+    /// Count how often the applied actions have an inverse, and how often
+    /// they fall back to a full copy of the state, per variant of the action.
+    ///
+    /// The variant is read from the `Debug` output of the action, so this is
+    /// meant for actions that are enums. Other actions are counted under the
+    /// name of their type. The bytes copied are the size of the state itself,
+    /// without the heap memory it owns. Use `track_inverses_with` to count
+    /// either differently. Tracking starts over with an empty report.
+    pub fn track_inverses(&mut self)
+    where
+        S: 'static,
+        S::Action: fmt::Debug,
+    {
+        self.track_inverses_with(Box::new(variant_name), Box::new(inline_size));
+    }
+
+    /// Count how often the applied actions have an inverse, like
+    /// `track_inverses`, per kind of action returned by `kind`, with the
+    /// bytes of a full copy returned by `size`.
+    pub fn track_inverses_with(&mut self, kind: KindFn<S::Action>, size: SizeFn<S>) {
+        self.inverses = Some(InverseTracker {
+            kind,
+            size,
+            report: InverseReport::default(),
+        });
+    }
+
+    /// Get the counts since tracking started, or `None` if the inverses are
+    /// not tracked.
+    pub fn inverse_report(&self) -> Option<&InverseReport> {
+        self.inverses.as_ref().map(|tracker| &tracker.report)
+    }
+
+    /// Stop tracking the inverses and return the counts.
+    pub fn stop_tracking_inverses(&mut self) -> Option<InverseReport> {
+        self.inverses.take().map(|tracker| tracker.report)
     }
 }
@@ -0,0 +1,89 @@
+mod helpers;
+use crate::helpers::counter::*;
+
+use actions::{audit_apply, audit_apply_hashed, State, Timeline, TimelineError};
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// A state whose actions read a global clock, which they should not.
+#[derive(Clone, Debug, PartialEq, Hash)]
+struct Stamped(u32);
+
+#[derive(Clone, Debug)]
+struct Stamp;
+
+static CLOCK: AtomicU32 = AtomicU32::new(0);
+
+impl State for Stamped {
+    type Action = Stamp;
+    type Error = ();
+
+    fn apply(&mut self, _: &Stamp) -> Result<(), ()> {
+        self.0 = CLOCK.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+fn audit_passes_deterministic_actions() {
+    let actions = vec![
+        CounterAction::Increment,
+        CounterAction::SetValue(4),
+        CounterAction::Multiply(3),
+        // Fails both times.
+        CounterAction::Decrement,
+    ];
+    for action in &actions {
+        let state = if let CounterAction::Decrement = action {
+            Counter(0)
+        } else {
+            Counter(2)
+        };
+        assert!(audit_apply(&state, action, || {}).is_ok());
+    }
+}
+
+#[test]
+fn audit_flags_hidden_inputs() {
+    let nondeterminism = audit_apply(&Stamped(0), &Stamp, || {
+        CLOCK.fetch_add(10, Ordering::SeqCst);
+    })
+    .unwrap_err();
+
+    let (first, second) = match (nondeterminism.first, nondeterminism.second) {
+        (Ok(first), Ok(second)) => (first, second),
+        _ => panic!("Expected both applies to succeed."),
+    };
+    assert!(second.0 > first.0);
+    assert!(audit_apply_hashed(&Stamped(0), &Stamp, || {}).is_err());
+}
+
+#[test]
+fn timeline_audit_passes() {
+    let mut t = Timeline::new(Counter::default());
+    t.enable_audit();
+    t.apply(CounterAction::Increment).unwrap();
+    t.apply(CounterAction::SetValue(0)).unwrap();
+    // The action fails on the copy too.
+    assert!(t.apply(CounterAction::Decrement).is_err());
+    t.undo().unwrap();
+    assert_eq!(t.current_state(), &Counter(1));
+}
+
+#[test]
+fn timeline_audit_refuses_nondeterminism() {
+    let mut t = Timeline::new(Stamped(0));
+    t.enable_audit_hashed();
+    let result = t.apply(Stamp);
+
+    if cfg!(debug_assertions) {
+        match result {
+            Err(TimelineError::Nondeterministic) => {}
+            result => panic!("Expected the audit to fail, got {:?}", result.err()),
+        }
+        assert_eq!(t.current_state(), &Stamped(0));
+        assert_eq!(t.undos_remaining(), 0);
+    } else {
+        assert!(result.is_ok());
+    }
+}
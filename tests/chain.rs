@@ -1,13 +1,18 @@
 use rand::distributions::Standard;
 use rand::prelude::{thread_rng, Distribution, Rng};
 
+use std::cell::Cell;
 use std::panic::catch_unwind;
+use std::rc::Rc;
 
 mod helpers;
 use crate::helpers::counter::*;
+use crate::helpers::position::*;
 
 use actions::Chain;
 use actions::Timeline;
+use actions::{Commute, Merge, MergeResult, State};
+use actions::{ErrorPolicy, FullCopyPolicy, InverseError};
 
 /// Clone the chain, compress one of them.
 /// Then execute the actions for both the uncompressed and the
@@ -97,7 +102,7 @@ fn chain_push() {
     chain.push(CounterAction::Increment);
     chain.push(CounterAction::Decrement);
 
-    let expected = vec![
+    let expected = [
         CounterAction::Increment,
         CounterAction::Decrement,
         CounterAction::Increment,
@@ -160,12 +165,13 @@ fn chain_compression_generated_long() {
 /// If the last item is overwriting, the chain should
 /// have a length of 1 after compression.
 fn chain_compression_last_overwriting() {
-    let mut chain = Vec::with_capacity(5);
-    chain.push(CounterAction::Increment);
-    chain.push(CounterAction::Decrement);
-    chain.push(CounterAction::Increment);
-    chain.push(CounterAction::Decrement);
-    chain.push(CounterAction::SetValue(5));
+    let chain = vec![
+        CounterAction::Increment,
+        CounterAction::Decrement,
+        CounterAction::Increment,
+        CounterAction::Decrement,
+        CounterAction::SetValue(5),
+    ];
 
     let mut chain: Chain<CounterAction> = chain.into();
     chain.compress();
@@ -173,3 +179,440 @@ fn chain_compression_last_overwriting() {
     assert_eq!(chain.len(), 1);
     assert_eq!(chain.actions()[0], CounterAction::SetValue(5));
 }
+
+#[test]
+fn chain_collection() {
+    let mut chain: Chain<CounterAction> = (1..=4).map(CounterAction::Multiply).collect();
+    assert_eq!(chain[1], CounterAction::Multiply(2));
+    assert_eq!(chain[2..].len(), 2);
+
+    chain.insert(0, CounterAction::Increment);
+    assert_eq!(chain.remove(1), CounterAction::Multiply(1));
+    chain.retain(|action| *action != CounterAction::Multiply(3));
+    chain.extend(vec![CounterAction::Decrement]);
+    chain[1] = CounterAction::Divide(2);
+
+    let expected = vec![
+        CounterAction::Increment,
+        CounterAction::Divide(2),
+        CounterAction::Multiply(4),
+        CounterAction::Decrement,
+    ];
+    assert_eq!(chain.iter().cloned().collect::<Vec<_>>(), expected);
+
+    let mut tail = chain.split_off(2);
+    assert_eq!(chain.len(), 2);
+    assert_eq!(tail.len(), 2);
+    chain.append(&mut tail);
+    assert!(tail.is_empty());
+    assert_eq!(chain, Chain::from(expected));
+
+    let removed: Vec<_> = chain
+        .splice(1..3, vec![CounterAction::SetValue(1)])
+        .collect();
+    assert_eq!(removed.len(), 2);
+    assert_eq!(
+        chain.into_iter().collect::<Vec<_>>(),
+        vec![
+            CounterAction::Increment,
+            CounterAction::SetValue(1),
+            CounterAction::Decrement
+        ]
+    );
+}
+
+/// A chain in which the third action fails on a counter that starts at 0.
+fn chain_with_failure() -> Chain<CounterAction> {
+    vec![
+        CounterAction::Increment,
+        CounterAction::Decrement,
+        CounterAction::Decrement,
+        CounterAction::SetValue(5),
+    ]
+    .into()
+}
+
+#[test]
+fn chain_apply_to() {
+    let chain = chain_with_failure();
+
+    let mut counter = Counter::default();
+    let report = chain.apply_to(&mut counter, ErrorPolicy::StopOnError);
+    assert_eq!(report.succeeded, vec![0, 1]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, 2);
+    assert!(!report.rolled_back);
+    assert_eq!(counter.0, 0);
+
+    let mut counter = Counter::default();
+    let report = chain.apply_to(&mut counter, ErrorPolicy::SkipFailing);
+    assert_eq!(report.succeeded, vec![0, 1, 3]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(counter.0, 5);
+
+    let mut counter = Counter(3);
+    let report = chain.apply_to_with_rollback(&mut counter, ErrorPolicy::RollbackOnError);
+    assert!(report.is_success());
+    assert_eq!(counter.0, 5);
+
+    let chain: Chain<CounterAction> = vec![
+        CounterAction::SetValue(4),
+        CounterAction::Increment,
+        CounterAction::Multiply(u32::MAX),
+    ]
+    .into();
+    let mut counter = Counter(1);
+    let report = chain.apply_to_with_rollback(&mut counter, ErrorPolicy::RollbackOnError);
+    assert!(report.rolled_back);
+    assert_eq!(report.succeeded, vec![0, 1]);
+    assert_eq!(counter.0, 1);
+
+    let mut counter = Counter(1);
+    let report = chain.apply_to_with_rollback(&mut counter, ErrorPolicy::StopOnError);
+    assert!(!report.rolled_back);
+    assert_eq!(counter.0, 5);
+}
+
+#[test]
+fn chain_apply_to_rolls_back_with_inverses() {
+    let chain: Chain<CounterAction> = vec![
+        CounterAction::Increment,
+        CounterAction::Multiply(2),
+        CounterAction::Multiply(u32::MAX),
+    ]
+    .into();
+    let mut counter = Counter(1);
+    let report = chain.apply_to(&mut counter, ErrorPolicy::RollbackOnError);
+    assert!(report.rolled_back);
+    assert_eq!(report.succeeded, vec![0, 1]);
+    assert_eq!(counter.0, 1);
+
+    // `SetValue` can only be undone with a full copy of the state.
+    let chain: Chain<CounterAction> = vec![
+        CounterAction::SetValue(4),
+        CounterAction::Multiply(u32::MAX),
+    ]
+    .into();
+    let mut counter = Counter(1);
+    let report = chain.apply_to(&mut counter, ErrorPolicy::RollbackOnError);
+    assert!(!report.rolled_back);
+    assert_eq!(counter.0, 4);
+}
+
+/// A log that can not be cloned, like a state that owns a file.
+struct Log(Vec<&'static str>);
+
+impl State for Log {
+    type Action = &'static str;
+    type Error = ();
+
+    fn apply(&mut self, line: &&'static str) -> Result<(), ()> {
+        if line.is_empty() {
+            return Err(());
+        }
+        self.0.push(line);
+        Ok(())
+    }
+}
+
+#[test]
+fn chain_apply_to_without_clone() {
+    let chain: Chain<&'static str> = vec!["first", "", "second"].into();
+    let mut log = Log(Vec::new());
+    let report = chain.apply_to(&mut log, ErrorPolicy::SkipFailing);
+    assert_eq!(report.succeeded, vec![0, 2]);
+    assert_eq!(log.0, vec!["first", "second"]);
+}
+
+#[test]
+fn chain_inverse() {
+    let chain: Chain<CounterAction> = vec![
+        CounterAction::Increment,
+        CounterAction::Multiply(3),
+        CounterAction::SetValue(7),
+        CounterAction::Decrement,
+    ]
+    .into();
+    let start = Counter(2);
+
+    // `SetValue` has no inverse action, restore it with another `SetValue`.
+    let restore = FullCopyPolicy::Restore(Box::new(|counter: &Counter| {
+        CounterAction::SetValue(counter.0)
+    }));
+    let inverse = chain.inverse(&start, restore).unwrap();
+    assert_eq!(
+        inverse.actions(),
+        &[
+            CounterAction::Increment,
+            CounterAction::SetValue(9),
+            CounterAction::Divide(3),
+            CounterAction::Decrement,
+        ]
+    );
+
+    let mut counter = start.clone();
+    assert!(chain
+        .apply_to(&mut counter, ErrorPolicy::StopOnError)
+        .is_success());
+    assert_eq!(counter.0, 6);
+    assert!(inverse
+        .apply_to(&mut counter, ErrorPolicy::StopOnError)
+        .is_success());
+    assert_eq!(counter.0, 2);
+
+    match chain.inverse(&start, FullCopyPolicy::Fail) {
+        Err(InverseError::FullCopyRequired { index }) => assert_eq!(index, 2),
+        _ => panic!("Expected the inverse to require a full copy."),
+    }
+}
+
+#[test]
+fn chain_inverse_with_capturing_restore() {
+    let chain: Chain<CounterAction> =
+        vec![CounterAction::SetValue(1), CounterAction::SetValue(2)].into();
+
+    // The function may capture its environment, here to count the calls.
+    let calls = Rc::new(Cell::new(0));
+    let counted = Rc::clone(&calls);
+    let restore = FullCopyPolicy::Restore(Box::new(move |counter: &Counter| {
+        counted.set(counted.get() + 1);
+        CounterAction::SetValue(counter.0)
+    }));
+
+    let inverse = chain.inverse(&Counter(5), restore).unwrap();
+    assert_eq!(
+        inverse.actions(),
+        &[CounterAction::SetValue(1), CounterAction::SetValue(5)]
+    );
+    assert_eq!(calls.get(), 2);
+}
+
+/// Steps and scale factors. Scaling by 1 is merged into the action before it.
+#[derive(Clone, Debug, PartialEq)]
+enum Scaled {
+    Step,
+    Times(u32),
+    Over(u32),
+}
+
+impl Merge for Scaled {
+    fn merge(&self, previous: &Scaled) -> MergeResult<Self> {
+        match (self, previous) {
+            (Scaled::Times(1), _) => MergeResult::Merged(previous.clone()),
+            (Scaled::Over(a), Scaled::Times(b)) if b % a == 0 => {
+                MergeResult::Merged(Scaled::Times(b / a))
+            }
+            _ => MergeResult::Unmergable,
+        }
+    }
+}
+
+#[test]
+fn chain_compress_fully() {
+    let chain: Chain<Scaled> = vec![Scaled::Step, Scaled::Times(3), Scaled::Over(3)].into();
+
+    // A single pass merges `Over(3)` into `Times(1)`, but does not
+    // merge the result with `Step`.
+    let mut single = chain.clone();
+    single.compress();
+    assert_eq!(single.len(), 2);
+
+    let mut full = chain.clone();
+    let stats = full.compress_fully();
+    assert_eq!(full.actions(), &[Scaled::Step]);
+    assert_eq!(stats.passes, 3);
+    assert_eq!(stats.merges, 2);
+    assert_eq!(stats.cancellations, 0);
+    assert_eq!(stats.len_before, 3);
+    assert_eq!(stats.len_after, 1);
+
+    // Compressing a fully compressed chain changes nothing.
+    let stats = full.compress_fully();
+    assert_eq!(stats.passes, 1);
+    assert_eq!(stats.len_before, stats.len_after);
+}
+
+#[test]
+/// An action that only overwrites the previous action must not drop
+/// the actions before it.
+fn chain_compression_overwrites_previous() {
+    let mut chain: Chain<PositionAction> = vec![
+        PositionAction::MoveY(4),
+        PositionAction::MoveX(1),
+        PositionAction::SetX(2),
+        PositionAction::SetX(3),
+    ]
+    .into();
+    chain.compress();
+
+    assert_eq!(
+        chain.actions(),
+        &[PositionAction::MoveY(4), PositionAction::SetX(3)]
+    );
+}
+
+#[test]
+fn chain_compression_mixed_overwrites() {
+    let chain: Chain<PositionAction> = vec![
+        PositionAction::MoveX(3),
+        PositionAction::MoveY(1),
+        PositionAction::SetX(5),
+        PositionAction::MoveY(1),
+        PositionAction::Teleport(0, 0),
+        PositionAction::MoveY(2),
+        PositionAction::MoveX(1),
+        PositionAction::SetX(7),
+    ]
+    .into();
+
+    let mut compressed = chain.clone();
+    let stats = compressed.compress_fully();
+    assert_eq!(
+        compressed.actions(),
+        &[
+            PositionAction::Teleport(0, 0),
+            PositionAction::MoveY(2),
+            PositionAction::SetX(7),
+        ]
+    );
+    assert_eq!(stats.overwrites, 2);
+
+    // Moving `SetX(5)` past `MoveY(1)` lets it overwrite `MoveX(3)`.
+    let mut commuted: Chain<PositionAction> = chain.actions()[..4].iter().cloned().collect();
+    commuted.compress_commuting();
+    assert_eq!(
+        commuted.actions(),
+        &[PositionAction::SetX(5), PositionAction::MoveY(2)]
+    );
+}
+
+/// Moves along two axes that are kept in a canonical order: X before Y.
+#[derive(Clone, Debug, PartialEq)]
+enum Canonical {
+    X(i64),
+    Y(i64),
+}
+
+impl Merge for Canonical {
+    fn merge(&self, previous: &Canonical) -> MergeResult<Self> {
+        match (self, previous) {
+            (Canonical::X(a), Canonical::Y(b)) => {
+                MergeResult::Replace(vec![Canonical::X(*a), Canonical::Y(*b)])
+            }
+            (Canonical::X(a), Canonical::X(b)) if a + b == 0 => MergeResult::CancelsOut,
+            (Canonical::X(a), Canonical::X(b)) => MergeResult::Merged(Canonical::X(a + b)),
+            (Canonical::Y(a), Canonical::Y(b)) => MergeResult::Merged(Canonical::Y(a + b)),
+            _ => MergeResult::Unmergable,
+        }
+    }
+}
+
+impl Commute for Canonical {
+    fn commutes_with(&self, _: &Canonical) -> bool {
+        true
+    }
+}
+
+/// Moves along two axes that swap places whenever they meet.
+#[derive(Clone, Debug, PartialEq)]
+struct Restless(Canonical);
+
+impl Merge for Restless {
+    fn merge(&self, previous: &Restless) -> MergeResult<Self> {
+        MergeResult::Replace(vec![self.clone(), previous.clone()])
+    }
+}
+
+fn total<'a, I: IntoIterator<Item = &'a Canonical>>(actions: I) -> (i64, i64) {
+    actions
+        .into_iter()
+        .fold((0, 0), |(x, y), action| match action {
+            Canonical::X(dx) => (x + dx, y),
+            Canonical::Y(dy) => (x, y + dy),
+        })
+}
+
+#[test]
+fn chain_compression_replace() {
+    let mut chain: Chain<Canonical> = vec![
+        Canonical::X(1),
+        Canonical::Y(2),
+        Canonical::X(2),
+        Canonical::Y(3),
+        Canonical::X(-3),
+    ]
+    .into();
+
+    let stats = chain.compress_fully();
+    assert_eq!(chain.actions(), &[Canonical::Y(5)]);
+    assert_eq!(stats.replacements, 2);
+    assert_eq!(stats.len_after, 1);
+}
+
+#[test]
+fn chain_compression_replace_terminates() {
+    let chain: Chain<Restless> = vec![
+        Restless(Canonical::X(1)),
+        Restless(Canonical::Y(2)),
+        Restless(Canonical::X(3)),
+    ]
+    .into();
+
+    let mut compressed = chain.clone();
+    compressed.compress_fully();
+    assert_eq!(compressed.len(), 3);
+    assert_eq!(
+        total(compressed.iter().map(|action| &action.0)),
+        total(chain.iter().map(|action| &action.0))
+    );
+}
+
+#[test]
+fn chain_compress_commuting_replace() {
+    let mut chain: Chain<Canonical> =
+        vec![Canonical::X(1), Canonical::Y(2), Canonical::X(2)].into();
+
+    // `X(2)` replaces `Y(2)`, and is merged with `X(1)` afterwards.
+    chain.compress_commuting();
+    assert_eq!(chain.actions(), &[Canonical::X(3), Canonical::Y(2)]);
+}
+
+/// Actions that only merge in a fixed pattern, to control every pass of
+/// compression.
+#[derive(Clone, Debug, PartialEq)]
+enum Letter {
+    A,
+    B,
+    C,
+    D,
+    E,
+    W,
+    X,
+    Y,
+    Z,
+}
+
+impl Merge for Letter {
+    fn merge(&self, previous: &Letter) -> MergeResult<Self> {
+        match (previous, self) {
+            (Letter::X, Letter::Y) => MergeResult::Merged(Letter::Z),
+            (Letter::W, Letter::Z) => MergeResult::CancelsOut,
+            (Letter::A, Letter::B) => MergeResult::Replace(vec![Letter::C, Letter::D, Letter::E]),
+            _ => MergeResult::Unmergable,
+        }
+    }
+}
+
+#[test]
+fn chain_compress_fully_continues_after_replace() {
+    let mut chain: Chain<Letter> =
+        vec![Letter::W, Letter::X, Letter::Y, Letter::A, Letter::B].into();
+
+    // The first pass merges `X` and `Y` into `Z`, but the replacement keeps
+    // the chain as long as it was. `Z` only cancels out `W` in the next pass.
+    let stats = chain.compress_fully();
+    assert_eq!(chain.actions(), &[Letter::C, Letter::D, Letter::E]);
+    assert_eq!(stats.passes, 3);
+    assert_eq!(stats.replacements, 1);
+    assert_eq!(stats.cancellations, 1);
+}
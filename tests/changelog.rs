@@ -0,0 +1,100 @@
+mod helpers;
+use crate::helpers::counter::*;
+
+use actions::{Chain, Changelog, Describe, Timeline, TimelineError};
+
+impl Describe for CounterAction {
+    fn kind(&self) -> String {
+        match self {
+            CounterAction::Increment | CounterAction::Decrement => "Steps".to_string(),
+            _ => "Arithmetic".to_string(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            CounterAction::Increment => "Counted up.".to_string(),
+            CounterAction::Decrement => "Counted down.".to_string(),
+            CounterAction::SetValue(v) => format!("Set the counter to {}.", v),
+            CounterAction::Divide(v) => format!("Divided by {}.", v),
+            CounterAction::Multiply(v) => format!("Multiplied by {}.", v),
+        }
+    }
+}
+
+#[test]
+fn changelog_groups_compressed_actions() {
+    let chain: Chain<CounterAction> = vec![
+        CounterAction::Multiply(6),
+        CounterAction::Increment,
+        CounterAction::Decrement,
+        CounterAction::Divide(2),
+        CounterAction::Increment,
+    ]
+    .into();
+
+    let changelog = Changelog::from_chain(&chain);
+
+    assert_eq!(changelog.len(), 2);
+    assert_eq!(changelog.groups()[0].kind, "Arithmetic");
+    assert_eq!(changelog.groups()[0].entries, vec!["Multiplied by 3."]);
+    assert_eq!(changelog.groups()[1].kind, "Steps");
+
+    assert_eq!(
+        changelog.to_text(),
+        "Arithmetic (1):\n  - Multiplied by 3.\n\nSteps (1):\n  - Counted up.\n"
+    );
+    assert_eq!(
+        changelog.to_markdown(),
+        "## Arithmetic\n\n- Multiplied by 3.\n\n## Steps\n\n- Counted up.\n"
+    );
+}
+
+#[test]
+fn changelog_between_saves() {
+    let mut t = Timeline::new(Counter::default());
+    t.apply(CounterAction::SetValue(3)).unwrap();
+    let first_save = t.undos_remaining();
+
+    t.apply(CounterAction::Increment).unwrap();
+    t.apply(CounterAction::Decrement).unwrap();
+    let second_save = t.undos_remaining();
+
+    // Nothing changed between the saves.
+    let chain = t.history_between(first_save, second_save).unwrap();
+    assert_eq!(chain.len(), 2);
+    assert!(Changelog::from_chain(&chain).is_empty());
+
+    t.apply(CounterAction::Multiply(2)).unwrap();
+    let chain = t.history_between(first_save, t.undos_remaining()).unwrap();
+    let changelog = Changelog::from_chain(&chain);
+    assert_eq!(changelog.groups()[0].entries, vec!["Multiplied by 2."]);
+}
+
+#[test]
+fn history_between_ignores_undone_actions() {
+    let mut t = Timeline::new(Counter::default());
+    t.apply(CounterAction::SetValue(3)).unwrap();
+    t.apply(CounterAction::Increment).unwrap();
+    t.apply(CounterAction::Multiply(2)).unwrap();
+    t.undo().unwrap();
+
+    let chain = t.history_between(0, t.undos_remaining()).unwrap();
+    assert_eq!(chain.len(), 2);
+
+    // The undone multiplication is only in the redo branch.
+    match t.history_between(0, 3) {
+        Err(TimelineError::InvalidRange { from: 0, to: 3 }) => {}
+        other => panic!(
+            "Expected an invalid range, got {:?}",
+            other.map(|c| c.len())
+        ),
+    }
+    match t.history_between(2, 1) {
+        Err(TimelineError::InvalidRange { from: 2, to: 1 }) => {}
+        other => panic!(
+            "Expected an invalid range, got {:?}",
+            other.map(|c| c.len())
+        ),
+    }
+}
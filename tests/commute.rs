@@ -0,0 +1,83 @@
+use rand::prelude::{thread_rng, Rng};
+
+mod helpers;
+use crate::helpers::position::*;
+
+use actions::{Chain, ErrorPolicy};
+
+/// Apply `chain` to the default position and return the result.
+fn end_position(chain: &Chain<PositionAction>) -> Position {
+    let mut position = Position::default();
+    assert!(chain
+        .apply_to(&mut position, ErrorPolicy::StopOnError)
+        .is_success());
+    position
+}
+
+#[test]
+fn commute_merges_non_adjacent_actions() {
+    let mut chain: Chain<PositionAction> = vec![
+        PositionAction::MoveX(1),
+        PositionAction::MoveY(2),
+        PositionAction::MoveX(-1),
+        PositionAction::MoveY(3),
+    ]
+    .into();
+
+    let mut greedy = chain.clone();
+    greedy.compress();
+    assert_eq!(greedy.len(), 4);
+
+    chain.compress_commuting();
+    assert_eq!(chain.actions(), &[PositionAction::MoveY(5)]);
+}
+
+#[test]
+fn commute_stops_at_non_commuting_action() {
+    let mut chain: Chain<PositionAction> = vec![
+        PositionAction::MoveX(1),
+        PositionAction::Teleport(0, 0),
+        PositionAction::MoveY(2),
+        PositionAction::MoveX(1),
+    ]
+    .into();
+
+    chain.compress_commuting();
+    assert_eq!(
+        chain.actions(),
+        &[
+            PositionAction::Teleport(0, 0),
+            PositionAction::MoveY(2),
+            PositionAction::MoveX(1),
+        ]
+    );
+}
+
+#[test]
+fn commute_compression_generated() {
+    let mut rng = thread_rng();
+
+    for _ in 0..200 {
+        let len = rng.gen_range(0, 50);
+        let chain: Chain<PositionAction> = (0..len)
+            .map(|_| match rng.gen_range(0, 20) {
+                0 => PositionAction::Teleport(rng.gen_range(-5, 5), rng.gen_range(-5, 5)),
+                1 | 2 => PositionAction::SetX(rng.gen_range(-5, 5)),
+                n if n % 2 == 0 => PositionAction::MoveX(rng.gen_range(-3, 3)),
+                _ => PositionAction::MoveY(rng.gen_range(-3, 3)),
+            })
+            .collect();
+
+        let mut compressed = chain.clone();
+        compressed.compress_commuting();
+
+        assert!(compressed.len() <= chain.len());
+        assert_eq!(
+            end_position(&chain),
+            end_position(&compressed),
+            "\n\nUNCOMPRESSED CHAIN:\n{:#?}\n\nCOMPRESSED CHAIN:\n{:#?}\n",
+            chain,
+            compressed
+        );
+    }
+}
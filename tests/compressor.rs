@@ -0,0 +1,173 @@
+use rand::prelude::{thread_rng, Rng};
+
+mod helpers;
+use crate::helpers::counter::*;
+use crate::helpers::position::*;
+
+use actions::{Chain, CompressActions, Compressor, Merge, MergeResult};
+
+fn random_actions(length: usize) -> Vec<CounterAction> {
+    let mut rng = thread_rng();
+    (0..length)
+        .map(|_| match rng.gen_range(0, 5) {
+            0 => CounterAction::Increment,
+            1 => CounterAction::Decrement,
+            2 => CounterAction::Divide(rng.gen_range(1, 4)),
+            3 => CounterAction::Multiply(rng.gen_range(1, 4)),
+            _ => CounterAction::SetValue(rng.gen_range(0, 10)),
+        })
+        .collect()
+}
+
+#[test]
+fn compressor_matches_compress() {
+    for _ in 0..50 {
+        let actions = random_actions(200);
+        let mut chain: Chain<CounterAction> = actions.clone().into();
+        chain.compress();
+
+        let mut compressor = Compressor::new();
+        let mut streamed = Vec::new();
+        for action in actions {
+            streamed.extend(compressor.push(action));
+        }
+        assert!(streamed.is_empty());
+        streamed.extend(compressor.finish());
+
+        assert_eq!(chain.actions(), &streamed[..]);
+    }
+}
+
+#[test]
+fn compressor_matches_compress_beyond_window() {
+    let mut rng = thread_rng();
+    for _ in 0..50 {
+        // These actions never cancel out and never overwrite the whole state,
+        // so they never need an action that left the window.
+        let actions: Vec<PositionAction> = (0..200)
+            .map(|_| match rng.gen_range(0, 3) {
+                0 => PositionAction::MoveX(rng.gen_range(1, 5)),
+                1 => PositionAction::MoveY(rng.gen_range(1, 5)),
+                _ => PositionAction::SetX(rng.gen_range(0, 10)),
+            })
+            .collect();
+        let mut chain: Chain<PositionAction> = actions.clone().into();
+        chain.compress();
+
+        let streamed: Vec<PositionAction> =
+            actions.into_iter().compress_actions_within(4).collect();
+        assert_eq!(chain.actions(), &streamed[..]);
+    }
+}
+
+/// An action that moves in front of every calm action it meets.
+#[derive(Clone, Debug, PartialEq)]
+enum Bubble {
+    Calm(usize),
+    Restless,
+}
+
+impl Merge for Bubble {
+    fn merge(&self, previous: &Bubble) -> MergeResult<Self> {
+        match (self, previous) {
+            (Bubble::Restless, Bubble::Calm(_)) => {
+                MergeResult::Replace(vec![Bubble::Restless, previous.clone()])
+            }
+            _ => MergeResult::Unmergable,
+        }
+    }
+}
+
+#[test]
+fn compressor_bounds_replacements_per_push() {
+    let mut actions: Vec<Bubble> = (0..32).map(Bubble::Calm).collect();
+    actions.push(Bubble::Restless);
+
+    let mut compressor = Compressor::new();
+    for action in actions.clone() {
+        compressor.push(action);
+    }
+
+    // Pushes without replacements do not save up for later pushes.
+    assert_eq!(compressor.stats().replacements, 8);
+    let tail: Vec<&Bubble> = compressor.tail().collect();
+    assert_eq!(tail[24], &Bubble::Restless);
+
+    // `Chain::compress` has the same budget for every action.
+    let mut chain: Chain<Bubble> = actions.into();
+    chain.compress();
+    assert_eq!(chain.actions(), &compressor.finish()[..]);
+}
+
+#[test]
+fn compressor_bounds_window() {
+    let mut compressor = Compressor::with_window(2);
+
+    assert!(compressor.push(PositionAction::MoveX(1)).is_empty());
+    assert!(compressor.push(PositionAction::MoveY(1)).is_empty());
+    assert!(compressor.push(PositionAction::MoveY(2)).is_empty());
+    assert_eq!(
+        compressor.push(PositionAction::MoveX(5)),
+        vec![PositionAction::MoveX(1)]
+    );
+    assert_eq!(compressor.tail().count(), 2);
+
+    assert!(compressor.push(PositionAction::MoveX(-5)).is_empty());
+    assert!(compressor.push(PositionAction::MoveY(-3)).is_empty());
+    assert_eq!(compressor.tail().count(), 0);
+
+    // This would have cancelled out the first action, but that action
+    // already left the window.
+    assert!(compressor.push(PositionAction::MoveX(-1)).is_empty());
+
+    let stats = compressor.stats();
+    assert_eq!(stats.len_before, 7);
+    assert_eq!(stats.merges, 1);
+    assert_eq!(stats.cancellations, 2);
+    assert_eq!(stats.len_after, 2);
+
+    assert_eq!(compressor.finish(), vec![PositionAction::MoveX(-1)]);
+}
+
+#[test]
+fn compressor_flush() {
+    let mut compressor = Compressor::with_window(8);
+    compressor.push(PositionAction::MoveX(1));
+    assert_eq!(compressor.flush(), vec![PositionAction::MoveX(1)]);
+
+    // Flushed actions are not merged with later actions.
+    compressor.push(PositionAction::MoveX(-1));
+    assert_eq!(compressor.finish(), vec![PositionAction::MoveX(-1)]);
+}
+
+#[test]
+fn compress_actions_iterator() {
+    for _ in 0..50 {
+        let actions = random_actions(100);
+        let mut chain: Chain<CounterAction> = actions.clone().into();
+        chain.compress();
+
+        let streamed: Vec<CounterAction> = actions.into_iter().compress_actions().collect();
+        assert_eq!(chain.actions(), &streamed[..]);
+    }
+
+    let streamed: Vec<PositionAction> = vec![
+        PositionAction::MoveX(1),
+        PositionAction::MoveY(1),
+        PositionAction::MoveX(-1),
+        PositionAction::MoveY(2),
+        PositionAction::MoveY(3),
+    ]
+    .into_iter()
+    .compress_actions_within(1)
+    .collect();
+    assert_eq!(
+        streamed,
+        vec![
+            PositionAction::MoveX(1),
+            PositionAction::MoveY(1),
+            PositionAction::MoveX(-1),
+            PositionAction::MoveY(5),
+        ]
+    );
+}
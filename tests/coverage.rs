@@ -0,0 +1,162 @@
+mod helpers;
+use crate::helpers::counter::*;
+
+use actions::{Entry, HistoryStore, InverseCounts, InverseResult, State, Timeline};
+
+use std::io;
+use std::mem::size_of;
+
+fn apply_all(t: &mut Timeline<Counter>) {
+    t.apply(CounterAction::Increment).unwrap();
+    t.apply(CounterAction::SetValue(4)).unwrap();
+    t.apply(CounterAction::Increment).unwrap();
+    t.apply(CounterAction::SetValue(6)).unwrap();
+    t.apply(CounterAction::Multiply(2)).unwrap();
+    // Failed actions are not counted.
+    t.apply(CounterAction::SetValue(0)).unwrap();
+    assert!(t.apply(CounterAction::Decrement).is_err());
+}
+
+#[test]
+fn report_counts_per_variant() {
+    let mut t = Timeline::new(Counter::default());
+    assert!(t.inverse_report().is_none());
+    t.track_inverses();
+    apply_all(&mut t);
+
+    let report = t.inverse_report().unwrap();
+    assert_eq!(
+        report.kinds["Increment"],
+        InverseCounts {
+            inverses: 2,
+            full_copies: 0,
+            bytes_copied: 0,
+        }
+    );
+    assert_eq!(
+        report.kinds["SetValue"],
+        InverseCounts {
+            inverses: 0,
+            full_copies: 3,
+            bytes_copied: 3 * size_of::<Counter>(),
+        }
+    );
+    assert!(!report.kinds.contains_key("Decrement"));
+    assert_eq!(report.total().inverses, 3);
+
+    let text = report.to_string();
+    assert!(text.contains("SetValue: 0 inverses, 3 full copies"));
+    assert!(text.ends_with("total: 3 inverses, 3 full copies (12 bytes)"));
+}
+
+#[test]
+fn report_with_custom_kind() {
+    let set = "set".to_string();
+    let bytes = 100;
+    let mut t = Timeline::new(Counter::default());
+    t.track_inverses_with(
+        Box::new(move |action| match action {
+            CounterAction::SetValue(_) => set.clone(),
+            _ => "step".to_string(),
+        }),
+        Box::new(move |_| bytes),
+    );
+    apply_all(&mut t);
+
+    let report = t.stop_tracking_inverses().unwrap();
+    assert_eq!(report.kinds.len(), 2);
+    assert_eq!(report.kinds["step"].inverses, 3);
+    assert_eq!(report.kinds["set"].bytes_copied, 300);
+    assert!(t.inverse_report().is_none());
+}
+
+/// A history store that refuses every entry.
+struct FullStore;
+
+impl HistoryStore<Counter> for FullStore {
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn push(&mut self, _: Entry<Counter>) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::WriteZero,
+            "the store is full",
+        ))
+    }
+
+    fn get(&self, _: usize) -> io::Result<Entry<Counter>> {
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "the store is empty",
+        ))
+    }
+
+    fn truncate(&mut self, _: usize) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn report_skips_actions_not_in_history() {
+    let mut t = Timeline::with_store(Counter::default(), FullStore);
+    t.track_inverses();
+    assert!(t.apply(CounterAction::Increment).is_err());
+    assert!(t.inverse_report().unwrap().kinds.is_empty());
+}
+
+/// A sum whose actions are plain numbers.
+#[derive(Clone, Debug, PartialEq)]
+struct Sum(i64);
+
+impl State for Sum {
+    type Action = i64;
+    type Error = ();
+
+    fn apply(&mut self, n: &i64) -> Result<(), ()> {
+        self.0 += n;
+        Ok(())
+    }
+
+    fn inverse(&self, n: &i64) -> InverseResult<i64> {
+        InverseResult::Action(-n)
+    }
+}
+
+#[test]
+fn report_names_non_enum_actions_by_type() {
+    let mut t = Timeline::new(Sum(0));
+    t.track_inverses();
+    t.apply(3).unwrap();
+    t.apply(-2).unwrap();
+
+    let report = t.inverse_report().unwrap();
+    assert_eq!(report.kinds.len(), 1);
+    assert_eq!(report.kinds["i64"].inverses, 2);
+}
+
+/// A switch whose actions are the value to switch to.
+#[derive(Clone, Debug, PartialEq)]
+struct Switch(bool);
+
+impl State for Switch {
+    type Action = bool;
+    type Error = ();
+
+    fn apply(&mut self, on: &bool) -> Result<(), ()> {
+        self.0 = *on;
+        Ok(())
+    }
+}
+
+#[test]
+fn report_names_bool_actions_by_type() {
+    let mut t = Timeline::new(Switch(false));
+    t.track_inverses();
+    t.apply(true).unwrap();
+    t.apply(false).unwrap();
+
+    let report = t.inverse_report().unwrap();
+    assert_eq!(report.kinds.len(), 1);
+    assert_eq!(report.kinds["bool"].full_copies, 2);
+}
@@ -0,0 +1,139 @@
+mod helpers;
+use crate::helpers::counter::*;
+
+use actions::{read_varint, write_varint};
+use actions::{Chain, ChainDecoder, ChainEncoder, Codec, Corruption, LoadError};
+
+use std::io::{self, Read, Write};
+
+/// A chain of `len` actions with only a few distinct actions in it.
+fn repetitive_chain(len: u32) -> Chain<CounterAction> {
+    let mut chain = Chain::new();
+    for i in 0..len {
+        chain.push(match i % 3 {
+            0 => CounterAction::Increment,
+            1 => CounterAction::Multiply(7),
+            _ => CounterAction::SetValue(i % 5),
+        });
+    }
+    chain
+}
+
+fn decode(bytes: &[u8]) -> Chain<CounterAction> {
+    Chain::read_from(bytes).unwrap()
+}
+
+#[test]
+fn encoding_varint() {
+    for value in [0, 1, 127, 128, 300, u64::from(u32::MAX), u64::MAX].iter() {
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, *value).unwrap();
+        assert_eq!(read_varint(&mut &bytes[..]).unwrap(), *value);
+    }
+
+    let mut bytes = Vec::new();
+    write_varint(&mut bytes, 127).unwrap();
+    assert_eq!(bytes.len(), 1);
+
+    // 11 continuation bytes can never be a valid u64.
+    assert!(read_varint(&mut &[0xff; 11][..]).is_err());
+}
+
+#[test]
+fn encoding_roundtrip() {
+    // Long enough to be split into several blocks.
+    let chain = repetitive_chain(5000);
+
+    let mut plain = Vec::new();
+    chain.write_to(&mut plain).unwrap();
+    let mut compact = Vec::new();
+    chain.write_to_with_dictionary(&mut compact, 16).unwrap();
+
+    assert_eq!(decode(&plain).actions(), chain.actions());
+    assert_eq!(decode(&compact).actions(), chain.actions());
+    assert!(compact.len() < plain.len());
+}
+
+#[test]
+fn encoding_dictionary_limit() {
+    let chain = repetitive_chain(100);
+
+    // A limit smaller than the number of distinct actions still roundtrips.
+    let mut bytes = Vec::new();
+    chain.write_to_with_dictionary(&mut bytes, 2).unwrap();
+    assert_eq!(decode(&bytes).actions(), chain.actions());
+}
+
+#[test]
+fn encoding_streaming() {
+    let mut encoder = ChainEncoder::with_dictionary(Vec::new(), 64).unwrap();
+    for i in 0..10_000 {
+        encoder.push(&CounterAction::Divide(i % 10)).unwrap();
+    }
+    let bytes = encoder.finish().unwrap();
+
+    let decoder = ChainDecoder::<_, CounterAction>::new(&bytes[..]).unwrap();
+    let mut count = 0;
+    for (i, action) in decoder.enumerate() {
+        assert_eq!(action.unwrap(), CounterAction::Divide(i as u32 % 10));
+        count += 1;
+    }
+    assert_eq!(count, 10_000);
+}
+
+#[test]
+fn encoding_refuses_tampered_data() {
+    let chain = repetitive_chain(50);
+    let mut bytes = Vec::new();
+    chain.write_to(&mut bytes).unwrap();
+
+    let middle = bytes.len() / 2;
+    bytes[middle] ^= 0x01;
+
+    match Chain::<CounterAction>::read_from(&bytes[..]) {
+        Err(LoadError::Corrupt(_)) => {}
+        _ => panic!("Expected the tampered chain to be refused."),
+    }
+}
+
+#[test]
+fn encoding_refuses_missing_trailer() {
+    let chain = repetitive_chain(50);
+    let mut bytes = Vec::new();
+    chain.write_to(&mut bytes).unwrap();
+
+    // The trailer is a single record: 4 + 2 + 4 + 8 bytes.
+    let length = bytes.len();
+    bytes.truncate(length - 18);
+
+    match Chain::<CounterAction>::read_from(&bytes[..]) {
+        Err(LoadError::Corrupt(_)) => {}
+        _ => panic!("Expected the truncated chain to be refused."),
+    }
+}
+
+/// An action that writes a padding byte it does not read back.
+#[derive(Clone, Debug, PartialEq)]
+struct Padded(u8);
+
+impl Codec for Padded {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[self.0, 0])
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        u8::decode(reader).map(Padded)
+    }
+}
+
+#[test]
+fn encoding_refuses_unread_action_bytes() {
+    let chain: Chain<Padded> = vec![Padded(1), Padded(2)].into();
+    let mut bytes = Vec::new();
+    chain.write_to(&mut bytes).unwrap();
+
+    match Chain::<Padded>::read_from(&bytes[..]) {
+        Err(LoadError::Corrupt(e)) => assert_eq!(e.kind, Corruption::BadLength),
+        _ => panic!("Expected the action with unread bytes to be refused."),
+    }
+}
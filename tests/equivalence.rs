@@ -0,0 +1,73 @@
+use rand::prelude::{thread_rng, Rng};
+
+mod helpers;
+use crate::helpers::counter::*;
+
+use actions::{check_equivalence, Chain, Outcome};
+
+#[test]
+fn equivalent_after_compress() {
+    let mut rng = thread_rng();
+
+    for _ in 0..20 {
+        // None of these actions can fail on these states, a compressed chain
+        // may succeed where the original chain fails.
+        let chain: Chain<CounterAction> = (0..rng.gen_range(0, 10))
+            .map(|_| match rng.gen_range(0, 4) {
+                0 => CounterAction::Increment,
+                1 => CounterAction::Multiply(rng.gen_range(1, 4)),
+                2 => CounterAction::Divide(rng.gen_range(1, 4)),
+                _ => CounterAction::SetValue(rng.gen_range(0, 10)),
+            })
+            .collect();
+        let mut compressed = chain.clone();
+        compressed.compress();
+
+        let checked = check_equivalence(&chain, &compressed, (0..200).map(Counter));
+        assert_eq!(checked.ok(), Some(200));
+    }
+}
+
+#[test]
+fn counterexample_when_merge_is_wrong() {
+    let chain: Chain<CounterAction> =
+        vec![CounterAction::Multiply(4), CounterAction::Divide(2)].into();
+    let mut compressed = chain.clone();
+    compressed.compress();
+    assert_eq!(compressed.actions(), &[CounterAction::Multiply(2)]);
+
+    // Multiplying by 4 overflows before multiplying by 2 does.
+    let states = (0..8).map(|i| Counter(u32::MAX / 8 * i));
+    let counterexample = check_equivalence(&chain, &compressed, states).unwrap_err();
+
+    assert_eq!(counterexample.checked, 3);
+    assert_eq!(counterexample.initial, Counter(u32::MAX / 8 * 3));
+    assert_eq!(
+        counterexample.left,
+        Outcome::Failed {
+            index: 0,
+            error: CounterError::WouldOverflow,
+        }
+    );
+    assert_eq!(
+        counterexample.right,
+        Outcome::Applied(Counter(u32::MAX / 8 * 6))
+    );
+    assert!(counterexample.to_string().contains("Counter(1610612733)"));
+}
+
+#[test]
+fn failing_on_both_sides_is_equivalent() {
+    let left: Chain<CounterAction> = vec![CounterAction::Decrement].into();
+    let right: Chain<CounterAction> = vec![
+        CounterAction::Increment,
+        CounterAction::Decrement,
+        CounterAction::Decrement,
+    ]
+    .into();
+
+    assert_eq!(
+        check_equivalence(&left, &right, vec![Counter(0), Counter(5)]).ok(),
+        Some(2)
+    );
+}
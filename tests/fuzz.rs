@@ -0,0 +1,46 @@
+use rand::prelude::{thread_rng, Rng};
+
+use actions::testing::{
+    check_laws, check_timeline, fuzz_compress, fuzz_timeline, Register, RegisterAction,
+};
+use actions::{Merge, MergeResult};
+
+fn random_bytes() -> Vec<u8> {
+    let mut rng = thread_rng();
+    (0..rng.gen_range(0, 200)).map(|_| rng.gen()).collect()
+}
+
+#[test]
+fn fuzz_compress_random() {
+    fuzz_compress(&[]);
+    fuzz_compress(&[0xff; 64]);
+    for _ in 0..500 {
+        fuzz_compress(&random_bytes());
+    }
+}
+
+#[test]
+fn fuzz_timeline_random() {
+    fuzz_timeline(&[]);
+    fuzz_timeline(&[0xff; 64]);
+    for _ in 0..500 {
+        fuzz_timeline(&random_bytes());
+    }
+}
+
+#[test]
+fn register_follows_laws() {
+    let result = check_laws::<Register>(11, 1000);
+    assert!(result.is_ok(), "{}", result.unwrap_err());
+
+    let result = check_timeline::<Register>(11, 50, 100);
+    assert!(result.is_ok(), "{}", result.unwrap_err());
+}
+
+#[test]
+fn register_keeps_division_by_zero() {
+    match RegisterAction::Div(0).merge(&RegisterAction::Set(3)) {
+        MergeResult::Unmergable => {}
+        _ => panic!("Dividing by zero should not be merged away."),
+    }
+}
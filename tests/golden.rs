@@ -0,0 +1,74 @@
+mod helpers;
+use crate::helpers::counter::*;
+
+use actions::testing::{
+    assert_golden_compression, compare_golden, render_compression, update_golden, GoldenError,
+};
+use actions::Chain;
+
+fn counter_macro() -> Chain<CounterAction> {
+    vec![
+        CounterAction::Increment,
+        CounterAction::Increment,
+        CounterAction::Decrement,
+        CounterAction::SetValue(3),
+        CounterAction::Multiply(2),
+        CounterAction::Increment,
+    ]
+    .into()
+}
+
+#[test]
+fn golden_compression() {
+    assert_golden_compression("tests/golden/counter_macro.txt", &counter_macro());
+}
+
+#[test]
+fn golden_reports_diff() {
+    let path = std::env::temp_dir().join(format!("actions-{}-golden.txt", std::process::id()));
+    update_golden(&path, &render_compression(&counter_macro())).unwrap();
+    assert!(compare_golden(&path, &render_compression(&counter_macro())).is_ok());
+
+    let mut changed = counter_macro();
+    changed.push(CounterAction::Decrement);
+    let result = compare_golden(&path, &render_compression(&changed));
+    std::fs::remove_file(&path).unwrap();
+    match result {
+        Err(GoldenError::Mismatch { diff, .. }) => {
+            assert!(diff.contains("- # 6 actions\n+ # 7 actions\n"));
+            assert!(diff.contains("+ Decrement\n"));
+        }
+        other => panic!("Expected a mismatch, got {:?}", other),
+    }
+
+    match compare_golden(&path, "") {
+        Err(GoldenError::Missing(missing)) => assert_eq!(missing, path),
+        other => panic!("Expected a missing golden file, got {:?}", other),
+    }
+}
+
+#[test]
+fn golden_diff_separates_hunks() {
+    let path = std::env::temp_dir().join(format!("actions-{}-hunks.txt", std::process::id()));
+    update_golden(&path, "a\nb\nc\nd\ne\nf\ng\n").unwrap();
+
+    let result = compare_golden(&path, "a\nB\nc\nd\ne\nF\ng\n");
+    std::fs::remove_file(&path).unwrap();
+    match result {
+        Err(GoldenError::Mismatch { diff, .. }) => {
+            assert_eq!(diff, "  a\n- b\n+ B\n  c\n@@\n  e\n- f\n+ F\n  g\n")
+        }
+        other => panic!("Expected a mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn golden_ignores_line_endings() {
+    let path = std::env::temp_dir().join(format!("actions-{}-crlf.txt", std::process::id()));
+    let rendered = render_compression(&counter_macro());
+    update_golden(&path, &rendered.replace('\n', "\r\n")).unwrap();
+
+    let result = compare_golden(&path, &rendered);
+    std::fs::remove_file(&path).unwrap();
+    assert!(result.is_ok());
+}
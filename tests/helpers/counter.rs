@@ -1,9 +1,10 @@
+use actions::Codec;
 use actions::InverseResult;
 use actions::State;
 
 use actions::{Merge, MergeResult};
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq)]
 pub struct Counter(pub u32);
 
 #[derive(Debug, Clone, PartialEq)]
@@ -15,7 +16,7 @@ pub enum CounterAction {
     Multiply(u32),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum CounterError {
     MinValueReached,
     MaxValueReached,
@@ -23,6 +24,7 @@ pub enum CounterError {
 }
 
 use std::fmt;
+use std::io::{self, Read, Write};
 impl fmt::Display for CounterError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -33,7 +35,7 @@ impl fmt::Display for CounterError {
             CounterError::MaxValueReached => write!(
                 f,
                 "Tried to increment while the maximum value of the counter was reached: {}.",
-                <u32>::max_value()
+                u32::MAX
             ),
             CounterError::WouldOverflow => write!(
                 f,
@@ -53,13 +55,13 @@ impl State for Counter {
     fn apply(&mut self, action: &CounterAction) -> Result<(), CounterError> {
         match action {
             CounterAction::Increment => {
-                if self.0 == <u32>::max_value() {
+                if self.0 == u32::MAX {
                     return Err(CounterError::MaxValueReached);
                 }
                 self.0 += 1;
             }
             CounterAction::Decrement => {
-                if self.0 == <u32>::min_value() {
+                if self.0 == u32::MIN {
                     return Err(CounterError::MinValueReached);
                 }
                 self.0 -= 1;
@@ -118,7 +120,49 @@ impl Merge for CounterAction {
                 _ => MergeResult::Unmergable,
             },
             CounterAction::Multiply(_) => MergeResult::Unmergable,
-            CounterAction::SetValue(_) => MergeResult::Overwrites,
+            CounterAction::SetValue(_) => MergeResult::OverwritesState,
         }
     }
 }
+
+impl Codec for Counter {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(Counter(u32::decode(reader)?))
+    }
+}
+
+impl Codec for CounterAction {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            CounterAction::Increment => 0u8.encode(writer),
+            CounterAction::Decrement => 1u8.encode(writer),
+            CounterAction::SetValue(v) => {
+                2u8.encode(writer)?;
+                v.encode(writer)
+            }
+            CounterAction::Divide(v) => {
+                3u8.encode(writer)?;
+                v.encode(writer)
+            }
+            CounterAction::Multiply(v) => {
+                4u8.encode(writer)?;
+                v.encode(writer)
+            }
+        }
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(match u8::decode(reader)? {
+            0 => CounterAction::Increment,
+            1 => CounterAction::Decrement,
+            2 => CounterAction::SetValue(u32::decode(reader)?),
+            3 => CounterAction::Divide(u32::decode(reader)?),
+            4 => CounterAction::Multiply(u32::decode(reader)?),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown action")),
+        })
+    }
+}
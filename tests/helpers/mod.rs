@@ -1,2 +1,4 @@
 #[allow(dead_code)]
 pub mod counter;
+#[allow(dead_code)]
+pub mod position;
@@ -0,0 +1,70 @@
+use actions::{Commute, Merge, MergeResult, State};
+
+/// A point on a grid. Moves along different axes commute.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Position {
+    pub x: i64,
+    pub y: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PositionAction {
+    MoveX(i64),
+    MoveY(i64),
+    SetX(i64),
+    Teleport(i64, i64),
+}
+
+impl State for Position {
+    type Action = PositionAction;
+    type Error = ();
+
+    fn apply(&mut self, action: &PositionAction) -> Result<(), ()> {
+        match action {
+            PositionAction::MoveX(dx) => self.x += dx,
+            PositionAction::MoveY(dy) => self.y += dy,
+            PositionAction::SetX(x) => self.x = *x,
+            PositionAction::Teleport(x, y) => {
+                self.x = *x;
+                self.y = *y;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Merge for PositionAction {
+    fn merge(&self, previous: &PositionAction) -> MergeResult<Self> {
+        match (self, previous) {
+            (PositionAction::Teleport(_, _), _) => MergeResult::OverwritesState,
+            (PositionAction::SetX(_), PositionAction::MoveX(_))
+            | (PositionAction::SetX(_), PositionAction::SetX(_)) => MergeResult::OverwritesPrevious,
+            (PositionAction::MoveX(a), PositionAction::SetX(b)) => {
+                MergeResult::Merged(PositionAction::SetX(a + b))
+            }
+            (PositionAction::MoveX(a), PositionAction::MoveX(b)) => match a + b {
+                0 => MergeResult::CancelsOut,
+                sum => MergeResult::Merged(PositionAction::MoveX(sum)),
+            },
+            (PositionAction::MoveY(a), PositionAction::MoveY(b)) => match a + b {
+                0 => MergeResult::CancelsOut,
+                sum => MergeResult::Merged(PositionAction::MoveY(sum)),
+            },
+            _ => MergeResult::Unmergable,
+        }
+    }
+}
+
+impl Commute for PositionAction {
+    fn commutes_with(&self, other: &PositionAction) -> bool {
+        match (self, other) {
+            (PositionAction::Teleport(_, _), _) | (_, PositionAction::Teleport(_, _)) => false,
+            // Moves along the same axis add up in any order.
+            (PositionAction::MoveX(_), PositionAction::MoveX(_))
+            | (PositionAction::MoveY(_), PositionAction::MoveY(_)) => true,
+            // Anything else only commutes with actions along the other axis.
+            (PositionAction::MoveY(_), _) | (_, PositionAction::MoveY(_)) => true,
+            _ => false,
+        }
+    }
+}
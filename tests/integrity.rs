@@ -0,0 +1,75 @@
+use actions::{verify_records, Corruption, IntegrityError, LoadError, Linking};
+use actions::{RecordReader, RecordWriter};
+
+/// Write `count` records with distinct payloads and return the bytes.
+fn write_records(count: usize, linking: Linking) -> Vec<u8> {
+    let mut writer = RecordWriter::new(Vec::new(), linking);
+    for i in 0..count {
+        writer.write_record(format!("record {}", i).as_bytes()).unwrap();
+    }
+    writer.into_inner()
+}
+
+/// Size in bytes of one record written by `write_records` with index < 10.
+fn record_size(linking: Linking) -> usize {
+    let link = match linking {
+        Linking::Unlinked => 0,
+        Linking::Chained => 8,
+    };
+    4 + "record 0".len() + 4 + link
+}
+
+fn expect_corrupt(bytes: &[u8], linking: Linking, record: usize, kind: Corruption) {
+    match verify_records(bytes, linking) {
+        Err(LoadError::Corrupt(e)) => assert_eq!(e, IntegrityError { record, kind }),
+        other => panic!("Expected record {} to be corrupt, got {:?}", record, other),
+    }
+}
+
+#[test]
+fn records_roundtrip() {
+    for linking in [Linking::Unlinked, Linking::Chained].iter() {
+        let bytes = write_records(5, *linking);
+        let mut reader = RecordReader::new(&bytes[..], *linking);
+
+        for i in 0..5 {
+            let payload = reader.read_record().unwrap().unwrap();
+            assert_eq!(payload, format!("record {}", i).into_bytes());
+        }
+        assert!(reader.read_record().unwrap().is_none());
+        assert_eq!(verify_records(&bytes[..], *linking).unwrap(), 5);
+    }
+}
+
+#[test]
+fn records_flipped_byte() {
+    let linking = Linking::Unlinked;
+    let mut bytes = write_records(5, linking);
+
+    // Damage the payload of the third record.
+    bytes[2 * record_size(linking) + 6] ^= 0xff;
+
+    expect_corrupt(&bytes, linking, 2, Corruption::ChecksumMismatch);
+}
+
+#[test]
+fn records_removed_record_breaks_chain() {
+    let linking = Linking::Chained;
+    let mut bytes = write_records(5, linking);
+
+    // Every record on its own is still intact, but record 1 is missing.
+    let size = record_size(linking);
+    bytes.drain(size..2 * size);
+
+    expect_corrupt(&bytes, linking, 1, Corruption::BrokenLink);
+}
+
+#[test]
+fn records_truncated() {
+    let linking = Linking::Chained;
+    let mut bytes = write_records(3, linking);
+    let length = bytes.len();
+    bytes.truncate(length - 3);
+
+    expect_corrupt(&bytes, linking, 2, Corruption::Truncated);
+}
@@ -0,0 +1,108 @@
+use rand::prelude::{thread_rng, Rng};
+
+mod helpers;
+use crate::helpers::counter::*;
+use crate::helpers::position::*;
+
+use actions::{Chain, Cost, ErrorPolicy, SearchBudget};
+
+/// Large moves are expensive, so merging moves is not always cheaper.
+impl Cost for PositionAction {
+    fn cost(&self) -> u64 {
+        match self {
+            PositionAction::MoveX(d) | PositionAction::MoveY(d) => 1 + (d * d) as u64,
+            _ => 1,
+        }
+    }
+}
+
+impl Cost for CounterAction {
+    fn cost(&self) -> u64 {
+        1
+    }
+}
+
+#[test]
+fn optimal_uses_cost() {
+    let chain: Chain<PositionAction> = vec![
+        PositionAction::MoveX(1),
+        PositionAction::MoveX(-1),
+        PositionAction::MoveX(5),
+        PositionAction::MoveX(5),
+        PositionAction::MoveY(1),
+        PositionAction::MoveY(1),
+    ]
+    .into();
+
+    let mut greedy = chain.clone();
+    greedy.compress_fully();
+    assert_eq!(
+        greedy.actions(),
+        &[PositionAction::MoveX(10), PositionAction::MoveY(2)]
+    );
+
+    // Merging `MoveX(-1)` with `MoveX(5)` instead of with `MoveX(1)`
+    // keeps every move small.
+    let mut optimal = chain.clone();
+    let stats = optimal.compress_optimal(SearchBudget::default());
+    assert_eq!(
+        optimal.actions(),
+        &[
+            PositionAction::MoveX(1),
+            PositionAction::MoveX(4),
+            PositionAction::MoveX(5),
+            PositionAction::MoveY(1),
+            PositionAction::MoveY(1),
+        ]
+    );
+    assert_eq!(stats.cost_before, 60);
+    assert_eq!(stats.cost_after, 2 + 17 + 26 + 2 + 2);
+    assert!(!stats.exhausted);
+}
+
+#[test]
+fn optimal_budget_exhausted() {
+    let chain: Chain<PositionAction> = (0..40).map(|_| PositionAction::MoveX(5)).collect();
+
+    let mut optimal = chain.clone();
+    let budget = SearchBudget {
+        window: 8,
+        max_evaluations: 10,
+    };
+    let stats = optimal.compress_optimal(budget);
+    assert!(stats.exhausted);
+    assert!(stats.cost_after <= stats.cost_before);
+}
+
+#[test]
+fn optimal_compression_generated() {
+    let mut rng = thread_rng();
+
+    for _ in 0..50 {
+        let chain: Chain<CounterAction> = (0..rng.gen_range(0, 40))
+            .map(|_| match rng.gen_range(0, 5) {
+                0 => CounterAction::Increment,
+                1 => CounterAction::Decrement,
+                2 => CounterAction::Divide(rng.gen_range(1, 4)),
+                3 => CounterAction::Multiply(rng.gen_range(1, 4)),
+                _ => CounterAction::SetValue(rng.gen_range(0, 10)),
+            })
+            .collect();
+
+        let mut greedy = chain.clone();
+        greedy.compress_fully();
+        let mut optimal = chain.clone();
+        optimal.compress_optimal(SearchBudget::default());
+        assert!(optimal.len() <= greedy.len());
+
+        let mut expected = Counter(100);
+        let mut actual = Counter(100);
+        let report = chain.apply_to(&mut expected, ErrorPolicy::StopOnError);
+        if report.is_success() {
+            assert!(optimal
+                .apply_to(&mut actual, ErrorPolicy::StopOnError)
+                .is_success());
+            assert_eq!(expected.0, actual.0, "\n{:?}\n{:?}\n", chain, optimal);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+mod helpers;
+use crate::helpers::counter::*;
+
+use actions::{Corruption, FormatHeader, LoadError, Timeline};
+
+/// A timeline with 4 entries of which 2 are undone.
+/// `SetValue` makes the timeline store a full copy as breadcrumb.
+fn saved_timeline() -> Vec<u8> {
+    let mut t = Timeline::new(Counter::default());
+    t.apply(CounterAction::Increment).unwrap();
+    t.apply(CounterAction::SetValue(10)).unwrap();
+    t.apply(CounterAction::Multiply(3)).unwrap();
+    t.apply(CounterAction::Increment).unwrap();
+    t.undo().unwrap();
+    t.undo().unwrap();
+    assert_eq!(t.current_state().0, 10);
+
+    let mut bytes = Vec::new();
+    t.save_to(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn persist_roundtrip() {
+    let bytes = saved_timeline();
+    let mut t = Timeline::<Counter>::load_from(&bytes[..]).unwrap();
+
+    assert_eq!(t.current_state().0, 10);
+    assert_eq!(t.undos_remaining(), 2);
+    assert_eq!(t.redos_remaining(), 2);
+
+    t.redo().unwrap();
+    assert_eq!(t.current_state().0, 30);
+    t.redo().unwrap();
+    assert_eq!(t.current_state().0, 31);
+    assert!(t.redo().is_err());
+
+    t.undo().unwrap();
+    t.undo().unwrap();
+    t.undo().unwrap();
+    assert_eq!(t.current_state().0, 1);
+    t.undo().unwrap();
+    assert_eq!(t.current_state().0, 0);
+    assert!(t.undo().is_err());
+}
+
+#[test]
+fn persist_header() {
+    let bytes = saved_timeline();
+    let header = FormatHeader::read(&mut &bytes[..]).unwrap();
+
+    assert_eq!(header.magic, FormatHeader::TIMELINE_MAGIC);
+    assert_eq!(header.version, FormatHeader::TIMELINE_VERSION);
+}
+
+#[test]
+fn persist_refuses_tampered_data() {
+    let mut bytes = saved_timeline();
+    // Change the value stored in the current state (record 1).
+    // Header (6) + meta record (4 + 16 + 4 + 8) + length (4).
+    bytes[6 + 32 + 4] = 11;
+
+    match Timeline::<Counter>::load_from(&bytes[..]) {
+        Err(LoadError::Corrupt(e)) => {
+            assert_eq!(e.record, 1);
+            assert_eq!(e.kind, Corruption::ChecksumMismatch);
+        }
+        _ => panic!("Expected the tampered data to be refused."),
+    }
+}
+
+#[test]
+fn persist_refuses_truncated_data() {
+    let mut bytes = saved_timeline();
+    // Remove the last entry completely: 4 + 3 + 4 + 8 bytes.
+    let length = bytes.len();
+    bytes.truncate(length - 19);
+
+    match Timeline::<Counter>::load_from(&bytes[..]) {
+        Err(LoadError::Corrupt(e)) => assert_eq!(e.kind, Corruption::Truncated),
+        _ => panic!("Expected the truncated data to be refused."),
+    }
+}
+
+#[test]
+fn persist_refuses_unknown_version() {
+    let mut bytes = saved_timeline();
+    bytes[4] = 0xff;
+
+    match Timeline::<Counter>::load_from(&bytes[..]) {
+        Err(LoadError::UnsupportedVersion(_)) => {}
+        _ => panic!("Expected an unsupported version."),
+    }
+
+    match Timeline::<Counter>::load_from(&b"not a timeline"[..]) {
+        Err(LoadError::BadMagic) => {}
+        _ => panic!("Expected a bad magic."),
+    }
+}
@@ -0,0 +1,126 @@
+mod helpers;
+use crate::helpers::counter::*;
+
+use actions::{
+    FileStore, LoadError, OpOutcome, Recording, ReplayMismatch, SessionRecorder, Timeline,
+    TimelineOp,
+};
+
+/// Record a session that contains every kind of operation, and some that fail.
+fn record_session() -> SessionRecorder<Counter> {
+    let mut recorder = SessionRecorder::new(Counter(1));
+    recorder.apply(CounterAction::Increment).unwrap();
+    recorder.apply(CounterAction::SetValue(10)).unwrap();
+    recorder
+        .apply_chain(vec![CounterAction::Multiply(3), CounterAction::Decrement].into())
+        .unwrap();
+    recorder.undo().unwrap();
+    recorder.undo().unwrap();
+    assert!(recorder.redo().is_ok());
+    recorder.apply(CounterAction::Divide(2)).unwrap();
+    assert!(recorder.redo().is_err());
+    recorder.apply(CounterAction::SetValue(0)).unwrap();
+    assert!(recorder.apply(CounterAction::Decrement).is_err());
+    recorder
+}
+
+#[test]
+fn recorder_records_outcomes() {
+    let recorder = record_session();
+    let recording = recorder.recording();
+
+    assert_eq!(recording.initial, Counter(1));
+    assert_eq!(recording.final_state, Counter(0));
+    assert_eq!(recording.ops.len(), 10);
+    assert_eq!(recording.ops[3].op, TimelineOp::Undo);
+    assert_eq!(recording.ops[7].outcome, OpOutcome::NothingToRedo);
+    assert_eq!(recording.ops[9].outcome, OpOutcome::ApplyFailed);
+
+    assert_eq!(recorder.current_state(), &Counter(0));
+    assert_eq!(recorder.into_timeline().undos_remaining(), 5);
+}
+
+#[test]
+fn recording_roundtrip() {
+    let recorder = record_session();
+    let mut bytes = Vec::new();
+    recorder.write_to(&mut bytes).unwrap();
+
+    let recording = Recording::<Counter>::read_from(&bytes[..]).unwrap();
+    assert_eq!(recording, recorder.recording());
+    recording.assert_replays();
+
+    let timeline = recording.replay().unwrap();
+    assert_eq!(timeline.current_state(), &Counter(0));
+}
+
+#[test]
+fn recording_refuses_cut_off_data() {
+    let mut bytes = Vec::new();
+    record_session().write_to(&mut bytes).unwrap();
+
+    // Cut off the record with the final state.
+    bytes.truncate(bytes.len() - 20);
+    match Recording::<Counter>::read_from(&bytes[..]) {
+        Err(LoadError::Corrupt(_)) | Err(LoadError::Io(_)) => {}
+        _ => panic!("Expected the cut off recording to be refused."),
+    }
+}
+
+#[test]
+fn replay_reports_mismatch() {
+    let mut recording = record_session().recording();
+    recording.ops[7].outcome = OpOutcome::Succeeded;
+    assert_eq!(
+        recording.replay().err(),
+        Some(ReplayMismatch::Outcome {
+            step: 7,
+            expected: OpOutcome::Succeeded,
+            actual: OpOutcome::NothingToRedo,
+        })
+    );
+
+    let mut recording = record_session().recording();
+    recording.final_state = Counter(3);
+    assert_eq!(
+        recording.replay().err(),
+        Some(ReplayMismatch::FinalState {
+            expected: Counter(3),
+            actual: Counter(0),
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "The recording did not replay")]
+fn assert_replays_panics() {
+    let mut recording = record_session().recording();
+    // Without setting the value to 0, decrementing succeeds.
+    recording.ops.remove(8);
+    recording.assert_replays();
+}
+
+#[test]
+fn recorder_wraps_timeline() {
+    let path = std::env::temp_dir().join(format!("actions-{}-recorder", std::process::id()));
+    let mut timeline = Timeline::with_store(Counter(1), FileStore::create(&path).unwrap());
+    timeline.apply(CounterAction::Increment).unwrap();
+
+    let mut recorder = SessionRecorder::wrap(timeline);
+    recorder.apply(CounterAction::Multiply(3)).unwrap();
+    recorder.undo().unwrap();
+    recorder.undo().unwrap();
+    assert_eq!(recorder.timeline().redos_remaining(), 2);
+
+    // The action applied before wrapping is not part of the recording.
+    let recording = recorder.recording();
+    assert_eq!(recording.initial, Counter(2));
+    assert_eq!(recording.final_state, Counter(1));
+    match recording.replay() {
+        Err(ReplayMismatch::Outcome { step: 2, .. }) => {}
+        result => panic!("Expected the second undo to differ, got {:?}", result.err()),
+    }
+
+    drop(recorder);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,92 @@
+mod helpers;
+use crate::helpers::counter::*;
+
+use actions::testing::{Expect, Scenario, Step};
+use actions::{scenario, FileStore, OpOutcome, Timeline, TimelineOp};
+
+#[test]
+fn scenario_macro_builds_steps() {
+    let built = scenario![
+        apply(CounterAction::Increment) => 1,
+        undo => 0,
+        undo => Err(NothingToUndo),
+        redo => 1,
+    ];
+    let expected = Scenario::new()
+        .apply(CounterAction::Increment, 1)
+        .undo(0)
+        .fails(TimelineOp::Undo, OpOutcome::NothingToUndo)
+        .redo(1);
+    assert_eq!(built, expected);
+}
+
+#[test]
+fn scenario_runs() {
+    let mut t = Timeline::new(Counter::default());
+    scenario![
+        apply(CounterAction::Increment) => 1,
+        apply(CounterAction::SetValue(5)) => 5,
+        apply_chain(vec![CounterAction::Multiply(3), CounterAction::Decrement].into()) => 14,
+        undo => 15,
+        undo => 5,
+        redo => 15,
+        redo => 14,
+        redo => Err(NothingToRedo),
+        apply(CounterAction::SetValue(0)) => 0,
+        apply(CounterAction::Decrement) => Err(ApplyFailed),
+    ]
+    .run(&mut t, |counter| counter.0);
+    assert_eq!(t.undos_remaining(), 5);
+}
+
+#[test]
+fn scenario_runs_on_any_store() {
+    let path = std::env::temp_dir().join(format!("actions-{}-scenario", std::process::id()));
+    let mut t = Timeline::with_store(Counter::default(), FileStore::create(&path).unwrap());
+    scenario![
+        apply(CounterAction::Increment) => true,
+        undo => false,
+        redo => true,
+    ]
+    .run(&mut t, |counter| counter.0 > 0);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn scenario_reports_failing_step() {
+    let mut t = Timeline::new(Counter::default());
+    let failure = scenario![
+        apply(CounterAction::Increment) => 1,
+        undo => 0,
+        undo => 0,
+        redo => 1,
+    ]
+    .check(&mut t, |counter| counter.0)
+    .unwrap_err();
+
+    assert_eq!(failure.step, 2);
+    assert_eq!(failure.steps.len(), 3);
+    assert_eq!(
+        failure.steps[2],
+        Step {
+            op: TimelineOp::Undo,
+            expect: Expect::Value(0),
+        }
+    );
+    assert_eq!(failure.observed[2], (OpOutcome::NothingToUndo, 0));
+
+    let trace = failure.to_string();
+    assert!(trace.contains("0: Apply(Increment) -> Succeeded, 1"));
+    assert!(trace.contains("2: Undo -> NothingToUndo, 0  <- expected Value(0)"));
+}
+
+#[test]
+#[should_panic(expected = "The scenario failed: step 1")]
+fn scenario_panics_on_wrong_value() {
+    let mut t = Timeline::new(Counter::default());
+    scenario![
+        apply(CounterAction::Increment) => 1,
+        apply(CounterAction::Increment) => 3,
+    ]
+    .run(&mut t, |counter| counter.0);
+}
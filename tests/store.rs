@@ -0,0 +1,118 @@
+mod helpers;
+use crate::helpers::counter::*;
+
+use actions::{FileStore, HistoryStore, SpillStore, State, Timeline};
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Return a path in the temporary directory that is unique for this test.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("actions-{}-{}", std::process::id(), name))
+}
+
+/// Apply and undo on `t` and check the value after every step.
+///
+/// `SetValue` has no inverse, so it makes the timeline store full copies.
+fn run_history<H: HistoryStore<Counter>>(t: &mut Timeline<Counter, H>) {
+    t.apply(CounterAction::Increment).unwrap();
+    t.apply(CounterAction::SetValue(10)).unwrap();
+    t.apply(CounterAction::Increment).unwrap();
+    t.apply(CounterAction::Multiply(3)).unwrap();
+    assert_eq!(t.current_state().0, 33);
+    for index in 0..t.history().len() {
+        let entry = t.history().get(index).unwrap();
+        assert_eq!(t.history().get_action(index).unwrap(), entry.action);
+    }
+
+    t.undo().unwrap();
+    t.undo().unwrap();
+    t.undo().unwrap();
+    assert_eq!(t.current_state().0, 1);
+
+    t.redo().unwrap();
+    assert_eq!(t.current_state().0, 10);
+    t.redo().unwrap();
+    assert_eq!(t.current_state().0, 11);
+    t.undo().unwrap();
+    t.undo().unwrap();
+    assert_eq!(t.redos_remaining(), 3);
+
+    // Discards the redo branch.
+    t.apply(CounterAction::SetValue(4)).unwrap();
+    t.apply(CounterAction::Decrement).unwrap();
+    assert_eq!(t.current_state().0, 3);
+    assert_eq!(t.redos_remaining(), 0);
+
+    t.undo().unwrap();
+    t.undo().unwrap();
+    assert_eq!(t.current_state().0, 1);
+    t.undo().unwrap();
+    assert_eq!(t.current_state().0, 0);
+    assert_eq!(t.undos_remaining(), 0);
+}
+
+#[test]
+fn store_memory() {
+    let mut t = Timeline::new(Counter::default());
+    run_history(&mut t);
+}
+
+#[test]
+fn store_file() {
+    let path = temp_path("store_file");
+    let store = FileStore::create(&path).unwrap();
+    let mut t = Timeline::with_store(Counter::default(), store);
+
+    run_history(&mut t);
+    assert_eq!(t.history().len(), 3);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn store_spill() {
+    let path = temp_path("store_spill");
+    let store = SpillStore::create(&path, 1).unwrap();
+    let mut t = Timeline::with_store(Counter::default(), store);
+
+    run_history(&mut t);
+    assert_eq!(t.history().len(), 3);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+/// The number of times a `Copied` has been cloned.
+static COPIES: AtomicUsize = AtomicUsize::new(0);
+
+/// A state that counts how often it is cloned.
+#[derive(Default)]
+struct Copied(Counter);
+
+impl Clone for Copied {
+    fn clone(&self) -> Self {
+        COPIES.fetch_add(1, Ordering::SeqCst);
+        Copied(self.0.clone())
+    }
+}
+
+impl State for Copied {
+    type Action = CounterAction;
+    type Error = CounterError;
+
+    fn apply(&mut self, action: &CounterAction) -> Result<(), CounterError> {
+        self.0.apply(action)
+    }
+}
+
+#[test]
+fn store_memory_redo_copies_no_state() {
+    let mut t = Timeline::new(Copied::default());
+    t.apply(CounterAction::SetValue(10)).unwrap();
+    t.undo().unwrap();
+
+    let copies = COPIES.load(Ordering::SeqCst);
+    t.redo().unwrap();
+    assert_eq!(COPIES.load(Ordering::SeqCst), copies);
+    assert_eq!((t.current_state().0).0, 10);
+}
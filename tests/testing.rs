@@ -0,0 +1,485 @@
+use rand::prelude::{thread_rng, Rng};
+
+mod helpers;
+use crate::helpers::counter::*;
+use crate::helpers::position::*;
+
+use actions::testing::rand::rngs::StdRng;
+use actions::testing::{
+    check_associativity, check_cancels_out, check_compress_preserves, check_inverse_law,
+    check_laws, check_merge_law, check_overwrites, check_timeline, gen_chain, run_timeline_model,
+    seeded_rng, shrink_chain, GenAction, GenState, Law, Shrink,
+};
+use actions::{Chain, ErrorPolicy, Merge, MergeResult, Outcome, State, TimelineOp};
+
+/// A register with a `Merge` implementation that is wrong on purpose.
+#[derive(Clone, Debug, PartialEq)]
+struct Register(i64);
+
+#[derive(Clone, Debug, PartialEq)]
+enum RegisterAction {
+    Add(i64),
+    Double,
+}
+
+impl State for Register {
+    type Action = RegisterAction;
+    type Error = ();
+
+    fn apply(&mut self, action: &RegisterAction) -> Result<(), ()> {
+        match action {
+            RegisterAction::Add(n) => self.0 += n,
+            RegisterAction::Double => self.0 *= 2,
+        }
+        Ok(())
+    }
+}
+
+impl Merge for RegisterAction {
+    fn merge(&self, previous: &Self) -> MergeResult<Self> {
+        match (previous, self) {
+            (RegisterAction::Add(a), RegisterAction::Add(b)) => {
+                MergeResult::Merged(RegisterAction::Add(a + b))
+            }
+            // Wrong, the addition should be doubled too.
+            (RegisterAction::Add(a), RegisterAction::Double) => {
+                MergeResult::Replace(vec![RegisterAction::Double, RegisterAction::Add(*a)])
+            }
+            _ => MergeResult::Unmergable,
+        }
+    }
+}
+
+/// Return the numbers between 0 and `n` to shrink `n` to, smallest first.
+fn smaller_than(n: i64) -> Vec<i64> {
+    let mut smaller = vec![0, 1, n / 2, n - 1];
+    smaller.retain(|&m| 0 <= m && m < n);
+    smaller.dedup();
+    smaller
+}
+
+impl Shrink for Register {
+    fn shrink(&self) -> Vec<Self> {
+        smaller_than(self.0.abs())
+            .into_iter()
+            .map(|n| Register(n * self.0.signum()))
+            .collect()
+    }
+}
+
+impl Shrink for RegisterAction {
+    fn shrink(&self) -> Vec<Self> {
+        match self {
+            RegisterAction::Add(n) => smaller_than(n.abs())
+                .into_iter()
+                .map(|m| RegisterAction::Add(m * n.signum()))
+                .collect(),
+            RegisterAction::Double => Vec::new(),
+        }
+    }
+}
+
+impl Shrink for Counter {
+    fn shrink(&self) -> Vec<Self> {
+        smaller_than(self.0.into())
+            .into_iter()
+            .map(|n| Counter(n as u32))
+            .collect()
+    }
+}
+
+impl Shrink for CounterAction {
+    fn shrink(&self) -> Vec<Self> {
+        // Dividing or multiplying by 0 is not allowed, so shrink towards 1.
+        let operand = |v: &u32| smaller_than(i64::from(*v)).into_iter().filter(|&m| m > 0);
+        match self {
+            CounterAction::Divide(v) => operand(v)
+                .map(|m| CounterAction::Divide(m as u32))
+                .collect(),
+            CounterAction::Multiply(v) => operand(v)
+                .map(|m| CounterAction::Multiply(m as u32))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Shrink for Position {}
+
+impl Shrink for PositionAction {}
+
+impl GenState for Counter {
+    fn gen_state<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Counter(rng.gen_range(0, 1000))
+    }
+}
+
+impl GenAction<Counter> for CounterAction {
+    fn gen_action<R: Rng + ?Sized>(counter: &Counter, rng: &mut R) -> Self {
+        match rng.gen_range(0, 4) {
+            0 => CounterAction::Increment,
+            1 if counter.0 > 0 => CounterAction::Decrement,
+            2 => CounterAction::Multiply(rng.gen_range(1, 4)),
+            _ => CounterAction::Divide(rng.gen_range(1, 4)),
+        }
+    }
+}
+
+impl GenState for Position {
+    fn gen_state<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Position {
+            x: rng.gen_range(-10, 10),
+            y: rng.gen_range(-10, 10),
+        }
+    }
+}
+
+impl GenAction<Position> for PositionAction {
+    fn gen_action<R: Rng + ?Sized>(position: &Position, rng: &mut R) -> Self {
+        random_position_action_with(position, rng)
+    }
+}
+
+fn random_counter_action(counter: &Counter) -> CounterAction {
+    CounterAction::gen_action(counter, &mut thread_rng())
+}
+
+fn random_position_action(position: &Position) -> PositionAction {
+    random_position_action_with(position, &mut thread_rng())
+}
+
+fn random_position_action_with<R: Rng + ?Sized>(_: &Position, rng: &mut R) -> PositionAction {
+    match rng.gen_range(0, 4) {
+        0 => PositionAction::MoveX(rng.gen_range(-3, 4)),
+        1 => PositionAction::MoveY(rng.gen_range(-3, 4)),
+        2 => PositionAction::SetX(rng.gen_range(-3, 4)),
+        _ => PositionAction::Teleport(rng.gen_range(-3, 4), rng.gen_range(-3, 4)),
+    }
+}
+
+fn random_register_action(_: &Register) -> RegisterAction {
+    let mut rng = thread_rng();
+    match rng.gen_range(0, 2) {
+        0 => RegisterAction::Add(rng.gen_range(-3, 4)),
+        _ => RegisterAction::Double,
+    }
+}
+
+#[test]
+fn inverse_law() {
+    // Dividing loses the remainder, so multiplying does not undo it.
+    let violation = check_inverse_law(
+        1000,
+        || Counter(thread_rng().gen_range(0, 1000)),
+        random_counter_action,
+    )
+    .unwrap_err();
+
+    assert_eq!(violation.law, Law::Inverse);
+    assert!(violation.left.is_empty());
+    match violation.right.actions() {
+        [CounterAction::Divide(v), CounterAction::Multiply(_)] => {
+            assert_ne!(violation.state.0 % v, 0, "{}", violation)
+        }
+        right => panic!("unexpected inverse {:?}", right),
+    }
+}
+
+#[test]
+fn inverse_law_reports_minimal_case() {
+    let violation = check_inverse_law(
+        1000,
+        || Counter(thread_rng().gen_range(0, 1000)),
+        random_counter_action,
+    )
+    .unwrap_err();
+
+    // 1 is the smallest number that 2, the smallest divisor above 1, does
+    // not divide.
+    assert_eq!(violation.state, Counter(1), "{}", violation);
+    assert_eq!(
+        violation.right.actions(),
+        &[CounterAction::Divide(2), CounterAction::Multiply(2)]
+    );
+}
+
+#[test]
+fn merge_law() {
+    assert!(check_merge_law(1000, Position::default, random_position_action).is_ok());
+
+    let violation = check_merge_law(
+        1000,
+        || Register(thread_rng().gen_range(-10, 10)),
+        random_register_action,
+    )
+    .unwrap_err();
+    assert_eq!(violation.law, Law::Merge);
+    assert_eq!(violation.left[1], RegisterAction::Double);
+    assert_ne!(violation.left[0], RegisterAction::Add(0));
+}
+
+#[test]
+fn merge_law_reports_minimal_case() {
+    let violation = check_merge_law(
+        1000,
+        || Register(thread_rng().gen_range(-10, 10)),
+        random_register_action,
+    )
+    .unwrap_err();
+
+    // The state does not matter, and any addition but 0 breaks the law.
+    assert_eq!(violation.state, Register(0), "{}", violation);
+    match violation.left.actions() {
+        [RegisterAction::Add(n), RegisterAction::Double] => assert_eq!(n.abs(), 1),
+        left => panic!("unexpected actions {:?}", left),
+    }
+}
+
+#[test]
+fn compress_preserves() {
+    let result = check_compress_preserves(
+        200,
+        20,
+        || Counter(thread_rng().gen_range(0, 100)),
+        random_counter_action,
+    );
+    assert!(result.is_ok(), "{}", result.unwrap_err());
+
+    let violation = check_compress_preserves(
+        200,
+        20,
+        || Register(thread_rng().gen_range(-10, 10)),
+        random_register_action,
+    )
+    .unwrap_err();
+    assert_eq!(violation.law, Law::CompressPreserves);
+
+    // The failing chain is shrunk to the two actions that are merged wrongly.
+    assert_eq!(violation.left.len(), 2);
+    assert_ne!(violation.left[0], RegisterAction::Add(0));
+    assert_eq!(violation.left[1], RegisterAction::Double);
+    assert!(violation
+        .to_string()
+        .starts_with("CompressPreserves law broken"));
+}
+
+#[test]
+fn generation_is_deterministic() {
+    let generate = |seed| {
+        let mut rng: StdRng = seeded_rng(seed);
+        let state = Position::gen_state(&mut rng);
+        let chain = gen_chain(&state, 20, &mut rng);
+        (state, chain)
+    };
+
+    assert_eq!(generate(7), generate(7));
+    assert_ne!(generate(7), generate(8));
+}
+
+#[test]
+fn generated_chains_are_valid() {
+    let mut rng = seeded_rng(1);
+    for _ in 0..100 {
+        let mut counter = Counter::gen_state(&mut rng);
+        let chain = gen_chain(&counter, 20, &mut rng);
+        assert!(chain
+            .apply_to(&mut counter, ErrorPolicy::StopOnError)
+            .is_success());
+    }
+}
+
+#[test]
+fn shrink_failing_chain() {
+    let chain: Chain<i32> = (0..20).collect();
+
+    // Fails as long as both 3 and 11 remain.
+    let shrunk = shrink_chain(chain, |chain| {
+        chain.iter().any(|&x| x == 3) && chain.iter().any(|&x| x == 11)
+    });
+    assert_eq!(shrunk.actions(), &[3, 11]);
+}
+
+#[test]
+fn laws_from_seed() {
+    assert!(check_laws::<Position>(42, 500).is_ok());
+
+    let violation = check_laws::<Counter>(42, 500).unwrap_err();
+    assert_eq!(violation.law, Law::Inverse);
+
+    // The same seed finds the same case.
+    let again = check_laws::<Counter>(42, 500).unwrap_err();
+    assert_eq!(violation.case, again.case);
+    assert_eq!(violation.state, again.state);
+}
+
+#[test]
+fn timeline_matches_model() {
+    let ops = vec![
+        TimelineOp::Apply(PositionAction::MoveX(1)),
+        TimelineOp::ApplyChain(vec![PositionAction::MoveY(2), PositionAction::SetX(4)].into()),
+        TimelineOp::Undo,
+        TimelineOp::Undo,
+        TimelineOp::Undo,
+        TimelineOp::Undo,
+        TimelineOp::Redo,
+        TimelineOp::Redo,
+        TimelineOp::Apply(PositionAction::Teleport(1, 1)),
+        TimelineOp::Redo,
+    ];
+    assert_eq!(run_timeline_model(Position::default(), ops).ok(), Some(10));
+
+    let result = check_timeline::<Position>(3, 50, 100);
+    assert!(result.is_ok(), "{}", result.unwrap_err());
+}
+
+#[test]
+fn timeline_mismatch_is_shrunk() {
+    // Multiplying does not undo dividing when there was a remainder.
+    let mismatch = check_timeline::<Counter>(5, 50, 100).unwrap_err();
+
+    assert!(mismatch.ops.len() <= 3, "{}", mismatch);
+    assert_eq!(mismatch.ops.last(), Some(&TimelineOp::Undo));
+    assert_ne!(mismatch.expected.state, mismatch.actual.state);
+}
+
+/// A lamp with merges that are only right when the lamp starts off.
+#[derive(Clone, Debug, PartialEq)]
+struct Lamp(bool);
+
+#[derive(Clone, Debug, PartialEq)]
+enum LampAction {
+    TurnOn,
+    TurnOff,
+    Toggle,
+}
+
+impl State for Lamp {
+    type Action = LampAction;
+    type Error = ();
+
+    fn apply(&mut self, action: &LampAction) -> Result<(), ()> {
+        self.0 = match action {
+            LampAction::TurnOn => true,
+            LampAction::TurnOff => false,
+            LampAction::Toggle => !self.0,
+        };
+        Ok(())
+    }
+}
+
+impl Merge for LampAction {
+    fn merge(&self, previous: &Self) -> MergeResult<Self> {
+        match (previous, self) {
+            (LampAction::Toggle, LampAction::Toggle) => MergeResult::CancelsOut,
+            // Wrong, a lamp that was on ends up off.
+            (LampAction::TurnOn, LampAction::TurnOff) => MergeResult::CancelsOut,
+            // Wrong, toggling after turning on always turns off.
+            (LampAction::TurnOn, LampAction::Toggle) => MergeResult::OverwritesPrevious,
+            (_, LampAction::TurnOn) | (_, LampAction::TurnOff) => MergeResult::OverwritesState,
+            _ => MergeResult::Unmergable,
+        }
+    }
+}
+
+fn random_lamp() -> Lamp {
+    Lamp(thread_rng().gen())
+}
+
+fn random_lamp_action(_: &Lamp) -> LampAction {
+    match thread_rng().gen_range(0, 3) {
+        0 => LampAction::TurnOn,
+        1 => LampAction::TurnOff,
+        _ => LampAction::Toggle,
+    }
+}
+
+#[test]
+fn merge_algebra() {
+    assert!(check_associativity(500, Position::default, random_position_action).is_ok());
+    assert!(check_cancels_out(500, Position::default, random_position_action).is_ok());
+    assert!(check_overwrites(500, Position::default, random_position_action).is_ok());
+
+    let violation = check_associativity(500, random_lamp, random_lamp_action).unwrap_err();
+    assert_eq!(violation.law, Law::Associativity);
+    assert_ne!(violation.left.actions(), violation.right.actions());
+
+    let violation = check_cancels_out(500, random_lamp, random_lamp_action).unwrap_err();
+    assert_eq!(violation.law, Law::CancelsOut);
+    assert_eq!(
+        violation.right.actions(),
+        &[LampAction::TurnOn, LampAction::TurnOff]
+    );
+    assert_eq!(violation.state, Lamp(true));
+
+    let violation = check_overwrites(500, random_lamp, random_lamp_action).unwrap_err();
+    assert_eq!(violation.law, Law::OverwritesPrevious);
+    assert_eq!(violation.right.actions(), &[LampAction::Toggle]);
+    assert_eq!(violation.state, Lamp(false));
+}
+
+/// An angle in degrees.
+#[derive(Clone, Debug, PartialEq)]
+struct Angle(i32);
+
+#[derive(Clone, Debug, PartialEq)]
+struct Turn(i32);
+
+impl State for Angle {
+    type Action = Turn;
+    type Error = ();
+
+    fn apply(&mut self, action: &Turn) -> Result<(), ()> {
+        self.0 = (self.0 + action.0).rem_euclid(360);
+        Ok(())
+    }
+}
+
+impl Merge for Turn {
+    fn merge(&self, previous: &Self) -> MergeResult<Self> {
+        // Which turn it gives depends on the order of the merges, but the
+        // effect does not.
+        let turn = previous.0 + self.0;
+        MergeResult::Merged(Turn(if turn >= 360 { turn - 360 } else { turn }))
+    }
+}
+
+/// A meter that can be filled and drained.
+#[derive(Clone, Debug, PartialEq)]
+struct Meter(i32);
+
+#[derive(Clone, Debug, PartialEq)]
+struct Fill(i32);
+
+impl State for Meter {
+    type Action = Fill;
+    type Error = ();
+
+    fn apply(&mut self, action: &Fill) -> Result<(), ()> {
+        self.0 += action.0;
+        Ok(())
+    }
+}
+
+impl Merge for Fill {
+    fn merge(&self, previous: &Self) -> MergeResult<Self> {
+        // Wrong, the meter itself is not capped.
+        MergeResult::Merged(Fill((previous.0 + self.0).min(10)))
+    }
+}
+
+#[test]
+fn associativity_compares_effects() {
+    // Turn(-60) and Turn(300) are different actions with the same effect.
+    let mut turns = vec![200, 200, -100].into_iter().cycle();
+    let result = check_associativity(1, || Angle(0), |_| Turn(turns.next().unwrap()));
+    assert!(result.is_ok(), "{}", result.unwrap_err());
+
+    let mut fills = vec![8, 8, -8].into_iter().cycle();
+    let violation =
+        check_associativity(1, || Meter(0), |_| Fill(fills.next().unwrap())).unwrap_err();
+    assert_eq!(violation.law, Law::Associativity);
+    assert_eq!(violation.left.actions(), &[Fill(2)]);
+    assert_eq!(violation.right.actions(), &[Fill(8)]);
+    assert_eq!(violation.expected, Meter(2));
+    assert_eq!(violation.actual, Outcome::Applied(Meter(8)));
+}
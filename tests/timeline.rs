@@ -161,3 +161,26 @@ fn timeline_redos_remaining_2() {
     // ... So there should not be any redo's remaining.
     assert_eq!(t.redos_remaining(), 0);
 }
+
+#[test]
+fn timeline_redo_after_several_undos() {
+    let mut t = Timeline::new(Counter::default());
+    let test_values = vec![
+        (Perform::Action(CounterAction::Increment), 1),
+        (Perform::Action(CounterAction::Multiply(3)), 3),
+        (Perform::Action(CounterAction::SetValue(7)), 7),
+        (Perform::Undo, 3),
+        (Perform::Undo, 1),
+        (Perform::Undo, 0),
+        (Perform::Redo, 1),
+        (Perform::Redo, 3),
+        (Perform::Undo, 1),
+        (Perform::Redo, 3),
+        (Perform::Redo, 7),
+    ];
+
+    for (action, expected_value) in test_values {
+        change_and_expect(&mut t, action, expected_value);
+    }
+    assert!(t.redo().is_err());
+}